@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// What `start_download` does with a video that is already in the archive.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchivePolicy {
+    /// Refuse to enqueue it.
+    Skip,
+    /// Enqueue it, but emit `already-downloaded` so the UI can say so.
+    #[default]
+    Warn,
+    /// Ignore the archive.
+    Force,
+}
+
+/// Emitted as `already-downloaded` when the `Warn` policy lets a known video through.
+#[derive(Clone, Serialize, Debug)]
+pub struct ArchiveMatchPayload {
+    pub id: String,
+    pub title: String,
+    pub archive_id: String,
+}
+
+#[derive(Clone, Serialize, Debug)]
+pub struct ArchiveImport {
+    /// Entries that were not in the archive yet.
+    pub added: usize,
+    /// Lines that are not `<extractor> <id>`.
+    pub invalid: usize,
+    pub total: usize,
+}
+
+/// The key yt-dlp writes for a video: its extractor key in lowercase and its id,
+/// e.g. `youtube dQw4w9WgXcQ`.
+pub fn archive_id(extractor_key: &str, id: &str) -> Option<String> {
+    let extractor = extractor_key.trim().to_lowercase();
+    let id = id.trim();
+    if extractor.is_empty() || id.is_empty() || extractor.contains(char::is_whitespace) || id.contains(char::is_whitespace) {
+        return None;
+    }
+    Some(format!("{} {}", extractor, id))
+}
+
+fn parse_line(line: &str) -> Option<String> {
+    let mut parts = line.split_whitespace();
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(extractor), Some(id), None) => archive_id(extractor, id),
+        _ => None,
+    }
+}
+
+/// Videos that finished downloading, stored as a yt-dlp `--download-archive`
+/// file so it can be shared with the command line as is.
+pub struct DownloadArchive {
+    path: PathBuf,
+    entries: Mutex<HashSet<String>>,
+}
+
+impl DownloadArchive {
+    pub fn open(path: PathBuf) -> Self {
+        let entries = match fs::read_to_string(&path) {
+            Ok(text) => text.lines().filter_map(parse_line).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => {
+                log::error!("[ARCHIVE] Failed to read {}: {}", path.display(), e);
+                HashSet::new()
+            }
+        };
+        Self { path, entries: Mutex::new(entries) }
+    }
+
+    pub fn contains(&self, archive_id: &str) -> bool {
+        self.entries.lock().unwrap().contains(archive_id)
+    }
+
+    /// Records a finished download. Appends, like yt-dlp, so the file is
+    /// never rewritten for a single entry.
+    pub fn add(&self, archive_id: &str) -> Result<bool, String> {
+        let entry = parse_line(archive_id).ok_or_else(|| format!("Invalid archive entry '{}'", archive_id))?;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.contains(&entry) {
+            return Ok(false);
+        }
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(&self.path).map_err(|e| e.to_string())?;
+        writeln!(file, "{}", entry).map_err(|e| e.to_string())?;
+        entries.insert(entry);
+        Ok(true)
+    }
+
+    /// Merges a yt-dlp archive file into this one.
+    pub fn import(&self, source: &Path) -> Result<ArchiveImport, String> {
+        let text = fs::read_to_string(source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        let mut entries = self.entries.lock().unwrap();
+        let mut merged = entries.clone();
+        let mut invalid = 0;
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            match parse_line(line) {
+                Some(entry) => {
+                    merged.insert(entry);
+                }
+                None => invalid += 1,
+            }
+        }
+        let added = merged.len() - entries.len();
+        if added > 0 {
+            write_entries(&self.path, &merged)?;
+        }
+        log::info!("[ARCHIVE] Imported {} new entries from {} ({} invalid lines)", added, source.display(), invalid);
+        *entries = merged;
+        Ok(ArchiveImport { added, invalid, total: entries.len() })
+    }
+
+    /// Writes the archive to `target` and returns the number of entries.
+    pub fn export(&self, target: &Path) -> Result<usize, String> {
+        let entries = self.entries.lock().unwrap();
+        write_entries(target, &entries)?;
+        Ok(entries.len())
+    }
+}
+
+fn write_entries(path: &Path, entries: &HashSet<String>) -> Result<(), String> {
+    let mut lines: Vec<&str> = entries.iter().map(String::as_str).collect();
+    lines.sort_unstable();
+    let mut text = lines.join("\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    // Atomic Save Pattern: Write to temp file then rename
+    let temp_path = path.with_extension("txt.tmp");
+    fs::write(&temp_path, text).map_err(|e| e.to_string())?;
+    fs::rename(&temp_path, path).map_err(|e| e.to_string())
+}
@@ -0,0 +1,422 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::download::DownloadStatus;
+use crate::error::DownloadError;
+use crate::retry::RetryPolicy;
+
+/// Extensions yt-dlp writes for the media itself. Another download of the
+/// same video (audio only, another container) shares the name, so files with
+/// these are only ever matched by their exact name when deleting.
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "mp4", "mkv", "webm", "mov", "flv", "m4a", "mp3", "opus", "ogg", "wav", "flac", "aac",
+];
+/// Extensions of the files yt-dlp puts next to the media: thumbnails,
+/// subtitles and metadata.
+const SIDE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "vtt", "srt", "ass", "lrc", "json", "description"];
+/// Suffixes of unfinished downloads.
+const PARTIAL_SUFFIXES: &[&str] = &["part", "ytdl", "temp"];
+
+fn is_partial_suffix(segment: &str) -> bool {
+    PARTIAL_SUFFIXES.contains(&segment) || segment.starts_with("part-Frag")
+}
+
+/// A segment between the title and the extension, e.g. the `en` in
+/// `Title.en.vtt`, `f137` in `Title.f137.mp4.part` or `info` in `Title.info.json`.
+fn is_tag(segment: &str) -> bool {
+    let is_lang = |s: &str| {
+        let (lang, region) = s.split_once('-').unwrap_or((s, ""));
+        (2..=3).contains(&lang.len())
+            && lang.chars().all(|c| c.is_ascii_lowercase())
+            && region.chars().all(|c| c.is_ascii_alphanumeric())
+    };
+    let is_format = |s: &str| s.len() > 1 && s.starts_with('f') && s[1..].chars().all(|c| c.is_ascii_digit());
+    matches!(segment, "info" | "temp" | "live_chat") || is_lang(segment) || is_format(segment)
+}
+
+/// Whether `file_name` belongs to the download whose name without extension
+/// is `stem`. Media names only count with `include_media`, e.g. for the
+/// format-specific fragments of a merge (`Title.f137.mp4.part`).
+fn is_side_file(stem: &str, file_name: &str, include_media: bool) -> bool {
+    let Some(rest) = file_name.strip_prefix(stem).and_then(|r| r.strip_prefix('.')) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = rest.split('.').collect();
+    while parts.len() > 1 && parts.last().is_some_and(|p| is_partial_suffix(p)) {
+        parts.pop();
+    }
+    let Some((ext, tags)) = parts.split_last() else {
+        return false;
+    };
+    let ext = ext.to_lowercase();
+    let known = SIDE_EXTENSIONS.contains(&ext.as_str()) || (include_media && MEDIA_EXTENSIONS.contains(&ext.as_str()));
+    known && tags.len() <= 2 && tags.iter().all(|t| is_tag(t))
+}
+
+/// Deletes a finished or partial download together with its thumbnail,
+/// subtitles, metadata and `.part`/`.ytdl` fragments. The media itself is
+/// matched by its exact name, so other downloads of the same video in the
+/// folder stay. Returns what was removed.
+pub fn delete_download_files(media_path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(name), Some(stem)) = (
+        media_path.parent(),
+        media_path.file_name().and_then(|s| s.to_str()),
+        media_path.file_stem().and_then(|s| s.to_str()),
+    ) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut removed = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let matches = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|file| is_artifact_of(name, file) || is_side_file(stem, file, false));
+        if matches && path.is_file() {
+            match fs::remove_file(&path) {
+                Ok(()) => removed.push(path),
+                Err(e) => log::warn!("[CLEANUP] Could not delete {}: {}", path.display(), e),
+            }
+        }
+    }
+    log::info!("[CLEANUP] Deleted {} files for {}", removed.len(), media_path.display());
+    removed
+}
+
+/// The file a yt-dlp output line says is about to be written, if any:
+/// download and post-processor destinations, merge outputs, thumbnails,
+/// subtitles and metadata sidecars.
+pub fn announced_path(line: &str) -> Option<PathBuf> {
+    let line = line.trim();
+    let quoted = |rest: &str| rest.split('"').nth(1).map(str::to_string);
+    let path = if let Some((_, rest)) = line.split_once("Destination: ") {
+        Some(rest.to_string())
+    } else if let Some(rest) = line.strip_prefix("[Merger] Merging formats into ") {
+        quoted(rest)
+    } else if line.starts_with("[info] Writing ") {
+        line.split_once(" to: ").map(|(_, rest)| rest.to_string())
+    } else if let Some(rest) = line.strip_prefix("[ThumbnailsConvertor] Converting thumbnail ") {
+        // Converting thumbnail "X.webp" to jpg
+        let format = rest.rsplit(' ').next().unwrap_or_default();
+        quoted(rest).map(|source| Path::new(&source).with_extension(format).to_string_lossy().to_string())
+    } else {
+        None
+    };
+    path.map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).map(PathBuf::from)
+}
+
+/// Whether `file_name` is `name` itself or one of the temporaries yt-dlp
+/// derives from it: `.part`, `.part-FragN`, `.ytdl` and `stem.temp.ext`.
+fn is_artifact_of(name: &str, file_name: &str) -> bool {
+    if file_name == name || file_name == format!("{}.ytdl", name) {
+        return true;
+    }
+    if file_name.strip_prefix(name).is_some_and(|rest| rest.starts_with(".part")) {
+        return true;
+    }
+    let path = Path::new(name);
+    match (path.file_stem().and_then(|s| s.to_str()), path.extension().and_then(|e| e.to_str())) {
+        (Some(stem), Some(ext)) => file_name == format!("{}.temp.{}", stem, ext),
+        _ => false,
+    }
+}
+
+/// Which of a stopped task's files to delete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cleanup {
+    Keep,
+    /// Only `.part`/`.ytdl`/`.temp` files; finished media stays.
+    Partials,
+    Everything,
+}
+
+/// Whether a task may still continue its fragments: it has not finished, or
+/// it failed with an error that a retry could pick up again.
+pub fn is_resumable(status: &DownloadStatus, error: Option<&DownloadError>, policy: &RetryPolicy) -> bool {
+    match status {
+        DownloadStatus::Completed | DownloadStatus::Cancelled => false,
+        DownloadStatus::Error => error.is_some_and(|e| policy.should_retry(e.class(), 0)),
+        _ => true,
+    }
+}
+
+/// A cancel removes everything the task wrote. A failure keeps its fragments
+/// while a retry could still continue them (`resumable`), and otherwise
+/// removes only the fragments, so media that was already finished, e.g. a
+/// merge output whose post-processing failed, is never lost.
+pub fn cleanup_after(status: &DownloadStatus, resumable: bool) -> Cleanup {
+    match status {
+        DownloadStatus::Cancelled => Cleanup::Everything,
+        DownloadStatus::Error if !resumable => Cleanup::Partials,
+        _ => Cleanup::Keep,
+    }
+}
+
+/// Applies `cleanup` to the files a task announced; see `remove_artifacts`.
+pub fn clean_up(cleanup: Cleanup, artifacts: &[PathBuf], output_dir: &Path) -> Vec<PathBuf> {
+    match cleanup {
+        Cleanup::Keep => Vec::new(),
+        Cleanup::Partials => remove_artifacts(artifacts, output_dir, true),
+        Cleanup::Everything => remove_artifacts(artifacts, output_dir, false),
+    }
+}
+
+/// Deletes the files a task announced together with their temporaries, or
+/// with `partials_only` just the temporaries. Relative paths are resolved
+/// against `output_dir`, and anything that does not live inside it is left
+/// alone. Returns what was removed.
+pub fn remove_artifacts(artifacts: &[PathBuf], output_dir: &Path, partials_only: bool) -> Vec<PathBuf> {
+    let Ok(root) = fs::canonicalize(output_dir) else {
+        log::warn!("[CLEANUP] Output directory {} is not accessible, skipping", output_dir.display());
+        return Vec::new();
+    };
+
+    let mut removed = Vec::new();
+    for artifact in artifacts {
+        let path = root.join(artifact);
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name().and_then(|n| n.to_str())) else {
+            continue;
+        };
+        let Ok(dir) = fs::canonicalize(parent) else {
+            continue;
+        };
+        if !dir.starts_with(&root) {
+            log::warn!("[CLEANUP] Not touching {} outside of {}", path.display(), root.display());
+            continue;
+        }
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let candidate = entry.path();
+            let matches = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|file| is_artifact_of(name, file) && !(partials_only && file == name));
+            if matches && candidate.is_file() && !removed.contains(&candidate) {
+                match fs::remove_file(&candidate) {
+                    Ok(()) => removed.push(candidate),
+                    Err(e) => log::warn!("[CLEANUP] Could not delete {}: {}", candidate.display(), e),
+                }
+            }
+        }
+    }
+    log::info!("[CLEANUP] Removed {} temporary files in {}", removed.len(), root.display());
+    removed
+}
+
+/// A leftover fragment of a download that can no longer be resumed.
+#[derive(Clone, Serialize, Debug)]
+pub struct OrphanedFile {
+    pub path: String,
+    pub size: u64,
+    /// The task the fragment was matched to.
+    pub task_id: String,
+}
+
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct SweepReport {
+    pub files: Vec<OrphanedFile>,
+    pub reclaimable_bytes: u64,
+}
+
+/// Whether `file_name` is an unfinished-download fragment: `.part`,
+/// `.part-FragN`, `.ytdl`, or a `.temp.ext` merge/post-processing file.
+fn is_fragment(file_name: &str) -> bool {
+    let mut segments = file_name.rsplit('.');
+    let last = segments.next().unwrap_or_default();
+    is_partial_suffix(last) || segments.next() == Some("temp")
+}
+
+/// The name a download's files share, e.g. `Title` for both
+/// `Title.f137.mp4` and `Title.mp4`.
+pub fn download_stem(media_path: &Path) -> Option<String> {
+    let stem = media_path.file_stem()?.to_str()?;
+    Some(match stem.rsplit_once('.') {
+        Some((base, tag)) if is_tag(tag) => base.to_string(),
+        _ => stem.to_string(),
+    })
+}
+
+/// A download whose fragments the sweep should look for.
+pub struct SweepTarget {
+    pub task_id: String,
+    pub media_path: PathBuf,
+    /// Still queued, running or paused, or failed but retryable; its
+    /// fragments are never touched.
+    pub resumable: bool,
+}
+
+/// Finds fragments next to the given downloads that belong to a task which
+/// cannot be resumed. Files that match no task are left alone: the folders
+/// are shared with browsers and other tools that use the same suffixes.
+pub fn find_orphans(targets: &[SweepTarget]) -> SweepReport {
+    let dirs: HashSet<&Path> = targets.iter().filter_map(|t| t.media_path.parent()).collect();
+    let stems: Vec<(&SweepTarget, String)> = targets
+        .iter()
+        .filter_map(|t| download_stem(&t.media_path).map(|stem| (t, stem)))
+        .collect();
+
+    let mut report = SweepReport::default();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            if !is_fragment(&name) || !path.is_file() {
+                continue;
+            }
+            let owners: Vec<&SweepTarget> = stems
+                .iter()
+                .filter(|(t, stem)| t.media_path.parent() == Some(dir) && is_side_file(stem, &name, true))
+                .map(|(t, _)| *t)
+                .collect();
+            if owners.iter().any(|t| t.resumable) {
+                continue;
+            }
+            if let Some(owner) = owners.first() {
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                report.reclaimable_bytes += size;
+                report.files.push(OrphanedFile {
+                    path: path.to_string_lossy().to_string(),
+                    size,
+                    task_id: owner.task_id.clone(),
+                });
+            }
+        }
+    }
+    report
+}
+
+/// Deletes the given orphans, returning the ones that were removed.
+pub fn delete_orphans(files: Vec<OrphanedFile>) -> SweepReport {
+    let mut report = SweepReport::default();
+    for file in files {
+        match fs::remove_file(&file.path) {
+            Ok(()) => {
+                report.reclaimable_bytes += file.size;
+                report.files.push(file);
+            }
+            Err(e) => log::warn!("[CLEANUP] Could not delete {}: {}", file.path, e),
+        }
+    }
+    log::info!("[CLEANUP] Swept {} orphaned files ({} bytes)", report.files.len(), report.reclaimable_bytes);
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::{FailureClass, RetryPolicy};
+
+    /// An output directory holding a finished `Done.mp4` and an unfinished `Video.mp4`.
+    fn output_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vidflow-cleanup-{}-{}", name, uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        for file in ["Done.mp4", "Video.mp4.part", "Video.mp4.ytdl", "Video.mp4.part-Frag3"] {
+            fs::write(dir.join(file), b"data").unwrap();
+        }
+        dir
+    }
+
+    fn artifacts() -> Vec<PathBuf> {
+        vec![PathBuf::from("Done.mp4"), PathBuf::from("Video.mp4")]
+    }
+
+    #[test]
+    fn part_survives_error_then_retry() {
+        let dir = output_dir("retry");
+        let policy = RetryPolicy::default();
+
+        // A network failure that used up the automatic attempts can still be retried by hand
+        assert!(!policy.should_retry(FailureClass::Network, policy.max_attempts));
+        let resumable = policy.should_retry(FailureClass::Network, 0);
+        let removed = clean_up(cleanup_after(&DownloadStatus::Error, resumable), &artifacts(), &dir);
+
+        assert!(removed.is_empty());
+        assert!(DownloadStatus::Error.can_transition_to(&DownloadStatus::Queued));
+        assert!(dir.join("Video.mp4.part").exists());
+        assert!(dir.join("Video.mp4.ytdl").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn final_error_removes_fragments_but_keeps_media() {
+        let dir = output_dir("fatal");
+        let resumable = RetryPolicy::default().should_retry(FailureClass::Fatal, 0);
+        clean_up(cleanup_after(&DownloadStatus::Error, resumable), &artifacts(), &dir);
+
+        assert!(dir.join("Done.mp4").exists());
+        assert!(!dir.join("Video.mp4.part").exists());
+        assert!(!dir.join("Video.mp4.ytdl").exists());
+        assert!(!dir.join("Video.mp4.part-Frag3").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cancel_removes_everything() {
+        let dir = output_dir("cancel");
+        clean_up(cleanup_after(&DownloadStatus::Cancelled, true), &artifacts(), &dir);
+
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn sweep_keeps_fragments_of_retryable_errors() {
+        let dir = output_dir("sweep");
+        let policy = RetryPolicy::default();
+        let target = |id: &str, error: DownloadError| SweepTarget {
+            task_id: id.to_string(),
+            media_path: dir.join("Video.mp4"),
+            resumable: is_resumable(&DownloadStatus::Error, Some(&error), &policy),
+        };
+
+        let retryable = target("network", DownloadError::Network("timed out".to_string()));
+        assert!(find_orphans(&[retryable]).files.is_empty());
+
+        let fatal = target("fatal", DownloadError::Unavailable);
+        let mut found: Vec<_> = find_orphans(&[fatal]).files.into_iter().map(|f| f.path).collect();
+        found.sort();
+        let expected: Vec<_> = ["Video.mp4.part", "Video.mp4.part-Frag3", "Video.mp4.ytdl"]
+            .iter()
+            .map(|f| dir.join(f).to_string_lossy().to_string())
+            .collect();
+        assert_eq!(found, expected);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn deleting_a_download_keeps_other_downloads_of_the_same_video() {
+        let dir = output_dir("same-stem");
+        for file in ["Video.mp4", "Video.mp3", "Video.mkv.part", "Video.jpg", "Video.en.vtt", "Video.info.json"] {
+            fs::write(dir.join(file), b"data").unwrap();
+        }
+        let mut removed: Vec<_> = delete_download_files(&dir.join("Video.mp4"))
+            .into_iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        removed.sort();
+
+        assert_eq!(removed, ["Video.en.vtt", "Video.info.json", "Video.jpg", "Video.mp4", "Video.mp4.part", "Video.mp4.part-Frag3", "Video.mp4.ytdl"]);
+        assert!(dir.join("Video.mp3").exists());
+        assert!(dir.join("Video.mkv.part").exists());
+        assert!(dir.join("Done.mp4").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn queued_and_paused_tasks_keep_their_files() {
+        assert_eq!(cleanup_after(&DownloadStatus::Queued, false), Cleanup::Keep);
+        assert_eq!(cleanup_after(&DownloadStatus::Paused, false), Cleanup::Keep);
+    }
+}
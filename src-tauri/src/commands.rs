@@ -1,4 +1,14 @@
-use crate::download::DownloadManager;
+use crate::archive::{ArchiveImport, ArchivePolicy};
+use crate::cleanup::SweepReport;
+use crate::playlist::{PlaylistEnqueued, PlaylistPage, PlaylistProgressPayload, PlaylistRequest};
+use crate::conflict::ConflictPolicy;
+use crate::download::{BulkResult, DownloadManager, DownloadOptions, DownloadStatus, Priority, VideoMetadata};
+use crate::persistence::{HistoryPage, HistoryQuery, PersistenceManager};
+use crate::retry::RetryPolicy;
+use crate::routing::{RouteContext, RouteDecision, RoutingRule};
+use crate::schedule::Schedule;
+use crate::settings::{AppSettings, BandwidthLimits, DiskSpace, HostLimits, PauseStrategy};
+use crate::template::{output_ext, OutputTemplate, DEFAULT_TEMPLATE};
 use tauri::{AppHandle, State};
 
 #[tauri::command]
@@ -7,12 +17,16 @@ pub async fn start_download(
     state: State<'_, DownloadManager>,
     url: String,
     title: String,
-    path: Option<String>,
-    format_spec: Option<String>,
-    cookies: Option<String>,
+    mut options: DownloadOptions,
+    priority: Option<Priority>,
+    route: Option<RouteContext>,
 ) -> Result<String, String> {
+    if let Some(template) = &options.output_template {
+        OutputTemplate::parse(template)?;
+    }
+    state.apply_routing(&app, &url, &mut options, &route.unwrap_or_default());
     let id = uuid::Uuid::new_v4().to_string();
-    state.start_download(app, url, id.clone(), title, path, format_spec, cookies);
+    state.start_download(app, url, id.clone(), title, options, priority.unwrap_or_default())?;
     Ok(id)
 }
 
@@ -25,12 +39,50 @@ pub async fn get_video_metadata(
     state.get_video_metadata(app, url).await
 }
 
+/// One page of a playlist listing; `limit` is capped at the playlist guardrail.
+#[tauri::command]
+pub async fn get_playlist_page(
+    url: String,
+    offset: u32,
+    limit: u32,
+) -> Result<PlaylistPage, String> {
+    DownloadManager::get_playlist_page(&url, offset, limit).await
+}
+
+/// Streams a playlist from `offset` as `playlist-page` events; returns the stream id.
+#[tauri::command]
+pub async fn stream_playlist(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    url: String,
+    offset: u32,
+    page_size: Option<u32>,
+) -> Result<String, String> {
+    Ok(state.stream_playlist(app, url, offset, page_size.unwrap_or(crate::download::SYSTEM_GUARDRAILS.max_playlist_items)))
+}
+
+#[tauri::command]
+pub async fn cancel_playlist_stream(
+    state: State<'_, DownloadManager>,
+    id: String,
+) -> Result<(), String> {
+    if state.cancel_playlist_stream(&id) {
+        Ok(())
+    } else {
+        Err("Playlist stream not found or already finished".to_string())
+    }
+}
+
 #[tauri::command]
 pub async fn cancel_download(
+    app: AppHandle,
     state: State<'_, DownloadManager>,
     id: String,
 ) -> Result<(), String> {
     if state.cancel_download(&id) {
+        // Report back only once the process tree is gone and the task is Cancelled
+        state.wait_for_exit(&id).await;
+        state.persist_task(&app, &id);
         Ok(())
     } else {
         Err("Task not found or already terminated".to_string())
@@ -39,10 +91,12 @@ pub async fn cancel_download(
 
 #[tauri::command]
 pub async fn pause_download(
+    app: AppHandle,
     state: State<'_, DownloadManager>,
     id: String,
 ) -> Result<(), String> {
     if state.pause_download(&id) {
+        state.persist_task(&app, &id);
         Ok(())
     } else {
         Err("Could not pause task".to_string())
@@ -51,14 +105,420 @@ pub async fn pause_download(
 
 #[tauri::command]
 pub async fn resume_download(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    id: String,
+) -> Result<DownloadStatus, String> {
+    match state.resume_download(app.clone(), &id) {
+        Some(status) => {
+            state.persist_task(&app, &id);
+            Ok(status)
+        }
+        None => Err("Could not resume task".to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn retry_download(
+    app: AppHandle,
     state: State<'_, DownloadManager>,
     id: String,
 ) -> Result<(), String> {
-    if state.resume_download(&id) {
+    if state.retry_download(app, &id) {
         Ok(())
     } else {
-        Err("Could not resume task".to_string())
+        Err("Only failed tasks can be retried".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn pause_all(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+) -> Result<Vec<BulkResult>, String> {
+    Ok(state.pause_all(&app))
+}
+
+#[tauri::command]
+pub async fn resume_all(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+) -> Result<Vec<BulkResult>, String> {
+    Ok(state.resume_all(&app))
+}
+
+#[tauri::command]
+pub async fn cancel_queued(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+) -> Result<Vec<BulkResult>, String> {
+    Ok(state.cancel_queued(&app))
+}
+
+#[tauri::command]
+pub async fn retry_failed(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+) -> Result<Vec<BulkResult>, String> {
+    Ok(state.retry_failed(&app))
+}
+
+/// Enqueues the selected entries of a playlist as child tasks of a new group.
+#[tauri::command]
+pub async fn enqueue_playlist(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    request: PlaylistRequest,
+    options: DownloadOptions,
+    priority: Option<Priority>,
+    route: Option<RouteContext>,
+) -> Result<PlaylistEnqueued, String> {
+    if let Some(template) = &options.output_template {
+        OutputTemplate::parse(template)?;
     }
+    state.enqueue_playlist(&app, request, options, priority.unwrap_or_default(), &route.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn list_playlists(
+    state: State<'_, DownloadManager>,
+) -> Result<Vec<PlaylistProgressPayload>, String> {
+    Ok(state.playlist_progress())
+}
+
+#[tauri::command]
+pub async fn cancel_playlist(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    id: String,
+) -> Result<Vec<BulkResult>, String> {
+    Ok(state.cancel_playlist(&app, &id))
+}
+
+/// Re-queues the failed entries of a playlist group.
+#[tauri::command]
+pub async fn retry_playlist(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    id: String,
+) -> Result<Vec<BulkResult>, String> {
+    Ok(state.retry_playlist(&app, &id))
+}
+
+#[tauri::command]
+pub async fn clear_completed(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+) -> Result<Vec<BulkResult>, String> {
+    Ok(state.clear_completed(&app))
+}
+
+#[tauri::command]
+pub async fn remove_download(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    id: String,
+    delete_files: bool,
+) -> Result<(), String> {
+    match state.remove_downloads(&app, std::slice::from_ref(&id), delete_files).first() {
+        Some(result) if result.ok => Ok(()),
+        Some(_) => Err("Cannot remove an active download; cancel or pause it first".to_string()),
+        None => Err("Task not found".to_string()),
+    }
+}
+
+/// Removes every stored task matching `filter` (paging is ignored). Without
+/// explicit statuses only finished, failed and cancelled tasks are cleared.
+#[tauri::command]
+pub async fn clear_history(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    mut filter: HistoryQuery,
+    delete_files: bool,
+) -> Result<Vec<BulkResult>, String> {
+    if filter.statuses.is_empty() {
+        filter.statuses = vec![DownloadStatus::Completed, DownloadStatus::Error, DownloadStatus::Cancelled];
+    }
+    let ids = persistence.find_task_ids(&filter)?;
+    Ok(state.remove_downloads(&app, &ids, delete_files))
+}
+
+/// Lists leftover fragments of downloads that can no longer be resumed,
+/// without deleting anything.
+#[tauri::command]
+pub async fn scan_orphaned_files(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+) -> Result<SweepReport, String> {
+    Ok(state.scan_orphaned_files(&app))
+}
+
+/// Deletes the confirmed paths from a previous scan.
+#[tauri::command]
+pub async fn delete_orphaned_files(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    paths: Vec<String>,
+) -> Result<SweepReport, String> {
+    Ok(state.delete_orphaned_files(&app, &paths))
+}
+
+#[tauri::command]
+pub async fn get_queue(
+    state: State<'_, DownloadManager>,
+) -> Result<Vec<String>, String> {
+    Ok(state.queue_order())
+}
+
+#[tauri::command]
+pub async fn set_priority(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    id: String,
+    priority: Priority,
+) -> Result<Vec<String>, String> {
+    let changed = state.set_priority(&id, priority)?;
+    state.persist_tasks(&app, &changed);
+    Ok(state.queue_order())
+}
+
+#[tauri::command]
+pub async fn reorder_download(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    id: String,
+    index: usize,
+) -> Result<Vec<String>, String> {
+    let changed = state.reorder_download(&id, index)?;
+    state.persist_tasks(&app, &changed);
+    Ok(state.queue_order())
+}
+
+#[tauri::command]
+pub async fn move_download_to_top(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    id: String,
+) -> Result<Vec<String>, String> {
+    reorder_download(app, state, id, 0).await
+}
+
+#[tauri::command]
+pub async fn move_download_to_bottom(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    id: String,
+) -> Result<Vec<String>, String> {
+    reorder_download(app, state, id, usize::MAX).await
+}
+
+#[tauri::command]
+pub async fn get_retry_policy(
+    state: State<'_, DownloadManager>,
+) -> Result<RetryPolicy, String> {
+    Ok(state.retry_policy.lock().unwrap().clone())
+}
+
+#[tauri::command]
+pub async fn set_retry_policy(
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    policy: RetryPolicy,
+) -> Result<(), String> {
+    state.set_retry_policy(policy.clone())?;
+    persistence.update_settings(|s| s.retry_policy = policy)
+}
+
+#[tauri::command]
+pub async fn get_settings(
+    persistence: State<'_, PersistenceManager>,
+) -> Result<AppSettings, String> {
+    Ok(persistence.settings())
+}
+
+#[tauri::command]
+pub async fn set_max_concurrent(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    max: usize,
+) -> Result<(), String> {
+    state.set_max_concurrent(max)?;
+    persistence.update_settings(|s| s.max_concurrent = max)?;
+    // Raising the limit should start waiting tasks right away
+    state.process_queue(app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_host_limits(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    limits: HostLimits,
+) -> Result<(), String> {
+    state.set_host_limits(limits)?;
+    let applied = state.host_limits.lock().unwrap().clone();
+    persistence.update_settings(|s| s.host_limits = applied)?;
+    state.process_queue(app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_bandwidth_limits(
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    limits: BandwidthLimits,
+) -> Result<(), String> {
+    state.set_bandwidth_limits(limits.clone())?;
+    persistence.update_settings(|s| s.bandwidth = limits)?;
+    state.rebalance_bandwidth();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_pause_strategy(
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    strategy: PauseStrategy,
+) -> Result<(), String> {
+    state.set_pause_strategy(strategy)?;
+    persistence.update_settings(|s| s.pause_strategy = strategy)
+}
+
+#[tauri::command]
+pub async fn set_output_template(
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    template: Option<String>,
+) -> Result<(), String> {
+    state.set_output_template(template)?;
+    let template = state.output_template.lock().unwrap().clone();
+    persistence.update_settings(|s| s.output_template = template)
+}
+
+/// Renders a template against analysed metadata, as the relative path the
+/// download would get. Without `template` the current default is used.
+#[tauri::command]
+pub async fn preview_output_template(
+    state: State<'_, DownloadManager>,
+    template: Option<String>,
+    metadata: VideoMetadata,
+    format_spec: Option<String>,
+) -> Result<String, String> {
+    let template = template
+        .or_else(|| state.output_template.lock().unwrap().clone())
+        .unwrap_or_else(|| DEFAULT_TEMPLATE.to_string());
+    let parsed = OutputTemplate::parse(&template)?;
+    let ext = output_ext(&metadata, format_spec.as_deref());
+    let format_id = format_spec.as_deref().filter(|spec| *spec != "audio");
+    Ok(parsed.render(&metadata, format_id, &ext))
+}
+
+#[tauri::command]
+pub async fn set_routing_rules(
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    rules: Vec<RoutingRule>,
+) -> Result<(), String> {
+    state.set_routing_rules(rules.clone())?;
+    persistence.update_settings(|s| s.routing_rules = rules)
+}
+
+/// Where the download of `url` would be saved under the current rules,
+/// without enqueueing anything.
+#[tauri::command]
+pub async fn resolve_output_dir(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    url: String,
+    format_spec: Option<String>,
+    route: Option<RouteContext>,
+) -> Result<RouteDecision, String> {
+    let decision = state.route_output_dir(&app, &url, format_spec.as_deref(), None, &route.unwrap_or_default());
+    Ok(match decision {
+        Some((rule, dir)) => RouteDecision { output_dir: Some(dir.to_string_lossy().to_string()), rule: Some(rule) },
+        None => RouteDecision { output_dir: None, rule: None },
+    })
+}
+
+#[tauri::command]
+pub async fn set_archive_policy(
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    policy: ArchivePolicy,
+) -> Result<(), String> {
+    state.set_archive_policy(policy);
+    persistence.update_settings(|s| s.archive_policy = policy)
+}
+
+/// Merges a yt-dlp `--download-archive` file into the archive.
+#[tauri::command]
+pub async fn import_download_archive(
+    persistence: State<'_, PersistenceManager>,
+    path: String,
+) -> Result<ArchiveImport, String> {
+    persistence.archive().import(std::path::Path::new(&path))
+}
+
+/// Writes the archive as a yt-dlp `--download-archive` file; returns the entry count.
+#[tauri::command]
+pub async fn export_download_archive(
+    persistence: State<'_, PersistenceManager>,
+    path: String,
+) -> Result<usize, String> {
+    persistence.archive().export(std::path::Path::new(&path))
+}
+
+#[tauri::command]
+pub async fn set_conflict_policy(
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    policy: ConflictPolicy,
+) -> Result<(), String> {
+    state.set_conflict_policy(policy);
+    persistence.update_settings(|s| s.conflict_policy = policy)
+}
+
+#[tauri::command]
+pub async fn set_disk_space(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    disk_space: DiskSpace,
+) -> Result<(), String> {
+    state.set_disk_space(disk_space.clone())?;
+    persistence.update_settings(|s| s.disk_space = disk_space)?;
+    // A smaller margin may let held tasks start
+    state.process_queue(app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_schedule(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    persistence: State<'_, PersistenceManager>,
+    schedule: Schedule,
+) -> Result<(), String> {
+    state.set_schedule(schedule.clone())?;
+    persistence.update_settings(|s| s.schedule = schedule)?;
+    state.process_queue(app);
+    state.rebalance_bandwidth();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn start_download_now(
+    app: AppHandle,
+    state: State<'_, DownloadManager>,
+    id: String,
+) -> Result<(), String> {
+    let changed = state.start_now(&id)?;
+    state.persist_tasks(&app, &changed);
+    state.process_queue(app);
+    Ok(())
 }
 
 #[tauri::command]
@@ -68,6 +528,14 @@ pub async fn list_downloads(
     Ok(state.get_tasks())
 }
 
+#[tauri::command]
+pub async fn query_history(
+    persistence: State<'_, PersistenceManager>,
+    query: HistoryQuery,
+) -> Result<HistoryPage, String> {
+    persistence.query_history(&query)
+}
+
 #[tauri::command]
 pub async fn show_in_folder(path: String) -> Result<(), String> {
     let path_buf = std::path::PathBuf::from(&path);
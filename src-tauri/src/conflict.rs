@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// What to do when the file a download would produce already exists.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Keep the existing file and finish without downloading (yt-dlp's default).
+    #[default]
+    Skip,
+    /// Replace the existing file.
+    Overwrite,
+    /// Download to `Title (1).mp4`, `Title (2).mp4`, ...
+    Rename,
+    /// Move the existing file to `Title (v1).mp4` and download to the original name.
+    Version,
+}
+
+/// How a conflict was resolved, reported on the task's progress payload.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum ConflictOutcome {
+    Skipped { path: String },
+    Overwritten { path: String },
+    Renamed { path: String, original: String },
+    Versioned { path: String, previous: String },
+}
+
+/// The first `stem (label).ext` next to `path` that does not exist yet,
+/// where `label` is produced from 1, 2, ...
+pub fn free_name(path: &Path, label: impl Fn(u32) -> String) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, label(n), ext)))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// An output template that writes exactly to `path` minus its extension,
+/// leaving the extension to yt-dlp so format-specific intermediates still work.
+pub fn template_for(path: &Path) -> String {
+    path.with_extension("").to_string_lossy().replace('%', "%%") + ".%(ext)s"
+}
+
+/// Applies `Rename` or `Version` to an existing `target`. Returns the template
+/// to download with, if it changes, and the outcome to record. `Version` moves
+/// the file aside right away so yt-dlp downloads to the freed name; `restore`
+/// puts it back if that download fails or is cancelled.
+pub fn resolve(policy: ConflictPolicy, target: &Path) -> Result<(Option<String>, Option<ConflictOutcome>), String> {
+    let display = |p: &Path| p.to_string_lossy().to_string();
+    match policy {
+        ConflictPolicy::Rename => {
+            let renamed = free_name(target, |n| n.to_string());
+            let outcome = ConflictOutcome::Renamed { path: display(&renamed), original: display(target) };
+            Ok((Some(template_for(&renamed)), Some(outcome)))
+        }
+        ConflictPolicy::Version => {
+            let previous = free_name(target, |n| format!("v{}", n));
+            std::fs::rename(target, &previous)
+                .map_err(|e| format!("Could not move {} aside: {}", target.display(), e))?;
+            let outcome = ConflictOutcome::Versioned { path: display(target), previous: display(&previous) };
+            Ok((None, Some(outcome)))
+        }
+        // Handled by yt-dlp itself and detected from its output
+        ConflictPolicy::Skip | ConflictPolicy::Overwrite => Ok((None, None)),
+    }
+}
+
+/// Undoes a `Versioned` outcome by moving the previous file back, as long as
+/// nothing has been written under its name since. Returns whether it moved.
+pub fn restore(outcome: &ConflictOutcome) -> Result<bool, String> {
+    let ConflictOutcome::Versioned { path, previous } = outcome else {
+        return Ok(false);
+    };
+    let (path, previous) = (Path::new(path), Path::new(previous));
+    if path.exists() || !previous.exists() {
+        return Ok(false);
+    }
+    std::fs::rename(previous, path)
+        .map(|_| true)
+        .map_err(|e| format!("Could not restore {}: {}", path.display(), e))
+}
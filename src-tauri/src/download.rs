@@ -1,15 +1,24 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use std::io::Write;
 use std::fs;
 use tauri::{AppHandle, Emitter, Runtime, Manager};
-use tauri_plugin_shell::ShellExt;
 use tauri_plugin_shell::process::CommandEvent;
-#[cfg(mobile)]
-pub type Child = (); 
-#[cfg(not(mobile))]
-pub use tauri_plugin_shell::process::CommandChild as Child;
+use tokio::sync::Notify;
+use crate::archive::{self, ArchiveMatchPayload, ArchivePolicy};
+use crate::cleanup;
+use crate::playlist::{self, PlaylistChild, PlaylistEnqueued, PlaylistGroup, PlaylistPage, PlaylistPagePayload, PlaylistProgressPayload, PlaylistRef, PlaylistRequest};
+use crate::conflict::{self, ConflictOutcome, ConflictPolicy};
+use crate::error::DownloadError;
+use crate::process::ProcessHandle;
+use crate::retry::RetryPolicy;
+use crate::routing::{self, RouteContext, RouteInput, RoutingRule};
+use crate::schedule::Schedule;
+use crate::template::OutputTemplate;
+use crate::settings::{validate_max_concurrent, BandwidthLimits, DiskSpace, HostLimits, PauseStrategy};
 
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
@@ -41,17 +50,35 @@ impl DownloadStatus {
             (DownloadStatus::Downloading, DownloadStatus::Completed) => true,
             (DownloadStatus::Downloading, DownloadStatus::Error) => true,
             (DownloadStatus::Downloading, DownloadStatus::Cancelled) => true,
+            // Restarted in place, e.g. to pick up a new rate limit
+            (DownloadStatus::Downloading, DownloadStatus::Queued) => true,
             (DownloadStatus::Paused, DownloadStatus::Downloading) => true,
             (DownloadStatus::Paused, DownloadStatus::Cancelled) => true,
+            // Resuming a task whose process was stopped relaunches it through the queue
+            (DownloadStatus::Paused, DownloadStatus::Queued) => true,
+            // A pause that ends the process can lose the race against it finishing
+            (DownloadStatus::Paused, DownloadStatus::Completed) => true,
             (DownloadStatus::Merging, DownloadStatus::Completed) => true,
             (DownloadStatus::Merging, DownloadStatus::Error) => true,
             (DownloadStatus::Merging, DownloadStatus::Cancelled) => true,
-            // Terminal states stay terminal unless retried (which creates a new task or reset)
+            // Failed tasks may be re-queued by a retry; other terminal states stay terminal
+            (DownloadStatus::Error, DownloadStatus::Queued) => true,
             _ => false,
         }
     }
 }
 
+/// Decides where a task lands when it enters the queue. Declared low to high
+/// so the derived ordering can be compared directly.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VideoFormat {
     pub format_id: String,
@@ -72,6 +99,12 @@ pub struct PlaylistEntry {
     pub title: String,
     pub url: String,
     pub duration: Option<f64>,
+    /// 1-based position in the playlist.
+    #[serde(default)]
+    pub index: Option<u32>,
+    /// Download archive key, when the flat listing names the extractor.
+    #[serde(default)]
+    pub archive_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,6 +117,24 @@ pub struct VideoMetadata {
     pub formats: Vec<VideoFormat>,
     pub is_playlist: bool,
     pub entries: Option<Vec<PlaylistEntry>>,
+    #[serde(default)]
+    pub uploader: Option<String>,
+    #[serde(default)]
+    pub channel: Option<String>,
+    /// YYYYMMDD, as yt-dlp reports it.
+    #[serde(default)]
+    pub upload_date: Option<String>,
+    #[serde(default)]
+    pub extractor: Option<String>,
+    /// `<extractor> <id>` as yt-dlp writes it to a download archive.
+    #[serde(default)]
+    pub archive_id: Option<String>,
+    /// Size of the whole playlist, when the site reports it.
+    #[serde(default)]
+    pub total_entries: Option<u64>,
+    /// `entries` is only the first page; fetch the rest with `stream_playlist`.
+    #[serde(default)]
+    pub has_more: bool,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -97,14 +148,97 @@ pub struct DownloadProgressPayload {
     pub downloaded_bytes: Option<u64>,
     pub can_retry: Option<bool>,
     pub error_message: Option<String>,
+    pub error_code: Option<String>,
     pub final_path: Option<String>,
+    pub retry_count: u32,
+    pub conflict: Option<ConflictOutcome>,
     pub version: u32, // IPC Versioning
 }
 
+/// Outcome for one task of a bulk operation.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct BulkResult {
+    pub id: String,
+    /// False when the task was selected but could not be changed.
+    pub ok: bool,
+    pub status: DownloadStatus,
+}
+
+/// Emitted as `disk-space-low` when a task is held back because it would not
+/// fit, or when running downloads were paused because a folder is nearly full.
+#[derive(Clone, Serialize, Debug)]
+pub struct DiskSpacePayload {
+    pub path: String,
+    pub available: u64,
+    pub required: u64,
+    pub task_ids: Vec<String>,
+    /// True for the low-space pause, false for a held task.
+    pub paused: bool,
+}
+
+/// Options captured when a task is enqueued. Each task owns its own copy so
+/// the queue can start it later without borrowing another task's settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DownloadOptions {
+    pub output_dir: Option<String>,
+    pub format_spec: Option<String>,
+    pub cookies: Option<String>,
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    /// Bytes per second; further caps this task's share of the global limit.
+    #[serde(default)]
+    pub rate_limit: Option<u64>,
+    /// "Start now": the task may start outside the scheduled windows.
+    #[serde(default)]
+    pub ignore_schedule: bool,
+    /// Expected size in bytes, e.g. the chosen format's `filesize`.
+    #[serde(default)]
+    pub estimated_size: Option<u64>,
+    /// File name template, see `template::OutputTemplate`. Filled in from the
+    /// settings at enqueue time so a relaunch keeps writing to the same file.
+    #[serde(default)]
+    pub output_template: Option<String>,
+    /// Filled in from the settings at enqueue time, like the template.
+    #[serde(default)]
+    pub conflict_policy: Option<ConflictPolicy>,
+    /// `VideoMetadata::archive_id`; recorded in the download archive on completion.
+    #[serde(default)]
+    pub archive_id: Option<String>,
+    /// Enqueue even if the archive already has the video.
+    #[serde(default)]
+    pub ignore_archive: bool,
+    /// Set on the child tasks of `enqueue_playlist`.
+    #[serde(default)]
+    pub playlist: Option<PlaylistRef>,
+    /// Output template a `Rename` conflict resolved to, reused by later runs
+    /// of the task so they write to the same file.
+    #[serde(default)]
+    pub conflict_output: Option<String>,
+}
+
 pub struct DownloadManager {
     // Authoritative store of all tasks
     pub tasks: Arc<Mutex<HashMap<String, Arc<Mutex<DownloadTask>>>>>,
-    pub max_concurrent: usize,
+    pub max_concurrent: AtomicUsize,
+    pub host_limits: Mutex<HostLimits>,
+    pub bandwidth: Mutex<BandwidthLimits>,
+    pub schedule: Mutex<Schedule>,
+    pub pause_strategy: Mutex<PauseStrategy>,
+    pub disk_space: Mutex<DiskSpace>,
+    pub output_template: Mutex<Option<String>>,
+    pub routing_rules: Mutex<Vec<RoutingRule>>,
+    // Running `stream_playlist` fetches; removing an id stops it after the current page
+    playlist_streams: Mutex<HashSet<String>>,
+    pub conflict_policy: Mutex<ConflictPolicy>,
+    pub archive_policy: Mutex<ArchivePolicy>,
+    pub playlists: Mutex<HashMap<String, PlaylistGroup>>,
+    // Last aggregate sent per playlist group, so unchanged groups are not re-emitted
+    playlist_emitted: Mutex<HashMap<String, PlaylistProgressPayload>>,
+    // Tasks the free-space preflight is holding back, each reported once
+    space_held: Mutex<HashSet<String>>,
+    // Wakes the schedule timer early when the windows change
+    schedule_changed: Notify,
+    pub retry_policy: Mutex<RetryPolicy>,
 }
 
 pub struct DownloadTask {
@@ -117,12 +251,48 @@ pub struct DownloadTask {
     pub eta: Option<u64>,
     pub total_size: Option<u64>,
     pub downloaded_bytes: Option<u64>,
-    pub child: Option<Child>,
+    pub child: Option<ProcessHandle>,
     pub final_path: Option<std::path::PathBuf>,
+    pub options: DownloadOptions,
+    pub retry_count: u32,
+    pub error: Option<DownloadError>,
+    // History timestamps, unix milliseconds
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub file_size: Option<u64>,
+    pub extractor: Option<String>,
+    // Set while an automatic retry is backing off; the queue skips the task until then
+    pub next_attempt_at: Option<Instant>,
+    pub priority: Priority,
+    // Order among waiting tasks; ties fall back to creation time
+    pub queue_position: i64,
+    // `--limit-rate` the current process was started with
+    pub rate_limit: Option<u64>,
+    // Why the running process is being stopped; the task settles once it exits
+    pub stop_reason: Option<StopReason>,
+    // Files yt-dlp announced it would write, removed again on cancel or failure
+    pub artifacts: Vec<std::path::PathBuf>,
+    // How an existing file at the destination was dealt with
+    pub conflict: Option<ConflictOutcome>,
+    // Re-queued after its process was stopped mid-download (rate restart,
+    // resumed pause); the schedule only holds back work that has not started
+    pub schedule_bypass: bool,
+}
+
+/// Reasons the manager ends a yt-dlp process without it failing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StopReason {
+    /// The user cancelled; the task becomes Cancelled once the process group is gone.
+    Cancel,
+    /// Re-queue at the front, e.g. to apply a new rate limit.
+    Restart,
+    /// Kill-and-continue pause; resume relaunches with `--continue`.
+    Pause,
 }
 
 impl DownloadTask {
-    pub fn new(id: String, url: String, title: String) -> Self {
+    pub fn new(id: String, url: String, title: String, options: DownloadOptions) -> Self {
         Self {
             id,
             url,
@@ -135,27 +305,151 @@ impl DownloadTask {
             downloaded_bytes: None,
             child: None,
             final_path: None,
+            options,
+            retry_count: 0,
+            error: None,
+            created_at: now_millis(),
+            started_at: None,
+            finished_at: None,
+            file_size: None,
+            extractor: None,
+            next_attempt_at: None,
+            priority: Priority::Normal,
+            queue_position: 0,
+            rate_limit: None,
+            stop_reason: None,
+            artifacts: Vec::new(),
+            conflict: None,
+            schedule_bypass: false,
+        }
+    }
+
+    /// Re-queues a task whose process was stopped on purpose. It was running
+    /// already, so it may continue outside the scheduled windows.
+    fn requeue_stopped(&mut self) -> bool {
+        if !self.transition(DownloadStatus::Queued) {
+            return false;
+        }
+        self.schedule_bypass = true;
+        true
+    }
+
+    /// Moves a queued task to Preparing for a new run of its process.
+    fn begin_attempt(&mut self) -> bool {
+        if !self.transition(DownloadStatus::Preparing) {
+            return false;
+        }
+        self.next_attempt_at = None;
+        self.error = None;
+        self.finished_at = None;
+        self.schedule_bypass = false;
+        if self.started_at.is_none() {
+            self.started_at = Some(now_millis());
+        }
+        true
+    }
+
+    /// Whether the schedule keeps this queued task from starting right now.
+    fn held_by_schedule(&self, in_window: bool) -> bool {
+        !in_window && !self.options.ignore_schedule && !self.schedule_bypass
+    }
+
+    /// Deletes the files this task's yt-dlp runs announced, along with their
+    /// temporaries, within the task's output directory. Without that record
+    /// (e.g. after an app restart) only the destination's fragments go.
+    fn remove_artifacts(&mut self, cleanup: cleanup::Cleanup) {
+        if cleanup == cleanup::Cleanup::Keep {
+            return;
+        }
+        let artifacts = std::mem::take(&mut self.artifacts);
+        if artifacts.is_empty() {
+            if let Some(dest) = &self.final_path {
+                remove_partials(dest);
+            }
+            return;
+        }
+        cleanup::clean_up(cleanup, &artifacts, &output_dir(&self.options));
+    }
+
+    /// Moves a file the `Version` policy set aside back under its name when
+    /// this task stopped without producing a replacement.
+    fn restore_versioned(&mut self) {
+        let Some(outcome) = self.conflict.take() else { return };
+        match conflict::restore(&outcome) {
+            Ok(true) => log::info!("[DOWNLOAD] Restored the previous version for {}", self.id),
+            Ok(false) => self.conflict = Some(outcome),
+            Err(e) => {
+                log::error!("[DOWNLOAD] {}", e);
+                self.conflict = Some(outcome);
+            }
+        }
+    }
+
+    /// Snapshot of the task for the frontend.
+    pub fn to_payload(&self) -> DownloadProgressPayload {
+        DownloadProgressPayload {
+            id: self.id.clone(),
+            progress: self.progress,
+            speed: self.speed,
+            eta: self.eta,
+            status: self.status.clone(),
+            total_size: self.total_size,
+            downloaded_bytes: self.downloaded_bytes,
+            can_retry: Some(self.status == DownloadStatus::Error),
+            error_message: self.error.as_ref().map(|e| e.to_string()),
+            error_code: self.error.as_ref().map(|e| e.code().to_string()),
+            final_path: self.final_path.as_ref().map(|p| p.to_string_lossy().to_string()),
+            retry_count: self.retry_count,
+            conflict: self.conflict.clone(),
+            version: SYSTEM_GUARDRAILS.ipc_version,
         }
     }
 
+    fn queue_key(&self) -> (i64, u64, String) {
+        (self.queue_position, self.created_at, self.id.clone())
+    }
+
     pub fn transition(&mut self, next: DownloadStatus) -> bool {
         if self.status.can_transition_to(&next) || self.status == next {
+            if matches!(next, DownloadStatus::Completed | DownloadStatus::Error | DownloadStatus::Cancelled) {
+                self.finished_at = Some(now_millis());
+            }
             self.status = next;
             true
         } else {
             false
         }
     }
+
+    /// Bytes per second over the whole run, once the task has finished with a known size.
+    pub fn average_speed(&self) -> Option<u64> {
+        let elapsed_ms = self.finished_at?.checked_sub(self.started_at?)?;
+        if elapsed_ms == 0 {
+            return None;
+        }
+        Some(self.file_size? * 1000 / elapsed_ms)
+    }
 }
 
-pub async fn verify_media_integrity<R: Runtime>(_app: &AppHandle<R>, path: &std::path::Path) -> Result<(), String> {
+pub fn now_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
+
+/// Lower-cased host of `url` without a leading `www.`, used to group tasks by site.
+pub fn url_host(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_lowercase();
+    Some(host.strip_prefix("www.").map(str::to_string).unwrap_or(host))
+}
+
+pub async fn verify_media_integrity<R: Runtime>(_app: &AppHandle<R>, path: &std::path::Path) -> Result<(), DownloadError> {
     // 1. Basic check: Existence and non-zero size
     if !path.exists() {
-        return Err("Output file does not exist".to_string());
+        return Err(DownloadError::VerificationFailed("Output file does not exist".to_string()));
     }
-    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    let metadata = fs::metadata(path).map_err(|e| DownloadError::VerificationFailed(e.to_string()))?;
     if metadata.len() == 0 {
-        return Err("Output file is empty".to_string());
+        return Err(DownloadError::VerificationFailed("Output file is empty".to_string()));
     }
 
     // 2. Rigorous check: ffprobe container validity
@@ -179,27 +473,112 @@ pub async fn verify_media_integrity<R: Runtime>(_app: &AppHandle<R>, path: &std:
         .args(["-v", "error", "-show_format", "-show_streams", &path.to_string_lossy()])
         .output()
         .await
-        .map_err(|e| format!("Failed to execute ffprobe: {}", e))?;
+        .map_err(|e| DownloadError::VerificationFailed(format!("Failed to execute ffprobe: {}", e)))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        return Err(format!("Corrupt media container detected: {}", stderr));
+        return Err(DownloadError::VerificationFailed(format!("Corrupt media container detected: {}", stderr)));
     }
 
     Ok(())
 }
 
+/// Where yt-dlp writes a task's files. Without -P that is our working directory.
+fn output_dir(options: &DownloadOptions) -> std::path::PathBuf {
+    match &options.output_dir {
+        Some(dir) => std::path::PathBuf::from(dir),
+        None => std::env::current_dir().unwrap_or_default(),
+    }
+}
+
+/// Free bytes on the volume holding `dir`. yt-dlp creates missing folders, so
+/// the nearest existing ancestor is checked.
+fn available_space(dir: &std::path::Path) -> Option<u64> {
+    dir.ancestors().find(|p| p.exists()).and_then(|p| fs2::available_space(p).ok())
+}
+
+/// Asks yt-dlp for the file `args` would produce, without downloading anything.
+async fn predict_filename(yt_dlp_path: &str, args: &[&str]) -> Option<std::path::PathBuf> {
+    let mut cmd = tokio::process::Command::new(yt_dlp_path);
+    cmd.args(args).args(["--simulate", "--no-warnings", "--print", "filename"]);
+    #[cfg(windows)]
+    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = cmd.output().await.ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().map(str::trim).rfind(|l| !l.is_empty()).map(std::path::PathBuf::from)
+}
+
+/// The bundled yt-dlp next to the executable, or the one on `PATH`.
+fn yt_dlp_path() -> String {
+    let name = if cfg!(windows) { "yt-dlp.exe" } else { "yt-dlp" };
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|p| p.join(name)))
+        .filter(|p| p.exists())
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|| "yt-dlp".to_string())
+}
+
+/// Reads the `entries` of a flat playlist listing that starts at `offset`.
+fn parse_entries(v: &serde_json::Value, offset: u32) -> Vec<PlaylistEntry> {
+    v["entries"]
+        .as_array()
+        .map(|arr| {
+            arr.iter()
+                .enumerate()
+                .map(|(position, entry)| PlaylistEntry {
+                    id: entry["id"].as_str().unwrap_or_default().to_string(),
+                    title: entry["title"].as_str().unwrap_or_default().to_string(),
+                    url: entry["url"].as_str().unwrap_or_default().to_string(),
+                    duration: entry["duration"].as_f64(),
+                    index: Some(offset.saturating_add(position as u32 + 1)),
+                    archive_id: entry["ie_key"].as_str().zip(entry["id"].as_str()).and_then(|(ie, id)| archive::archive_id(ie, id)),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether entries remain after a page of `len` entries starting at `offset`.
+fn has_more_entries(offset: u32, limit: u32, len: usize, total: Option<u64>) -> bool {
+    len > 0 && total.map_or(len >= limit as usize, |total| (offset as u64 + len as u64) < total)
+}
+
+/// Removes the fragments yt-dlp keeps next to an unfinished download.
+fn remove_partials(dest: &std::path::Path) {
+    let _ = fs::remove_file(format!("{}.part", dest.display()));
+    let _ = fs::remove_file(format!("{}.ytdl", dest.display()));
+}
+
 pub struct Guardrails {
     pub max_concurrent_downloads: usize,
+    pub max_concurrent_limit: usize,
     pub max_playlist_items: u32,
     pub default_fragments: u32,
+    pub stderr_tail_lines: usize,
+    pub min_rate_limit: u64,
+    pub kill_grace_ms: u64,
+    pub disk_check_interval_secs: u64,
+    pub max_filename_len: usize,
+    pub playlist_progress_ms: u64,
     pub ipc_version: u32,
 }
 
 pub const SYSTEM_GUARDRAILS: Guardrails = Guardrails {
     max_concurrent_downloads: 2,
+    max_concurrent_limit: 10,
     max_playlist_items: 100,
     default_fragments: 8,
+    stderr_tail_lines: 50,
+    min_rate_limit: 32 * 1024,
+    kill_grace_ms: 3000,
+    disk_check_interval_secs: 15,
+    max_filename_len: 200,
+    playlist_progress_ms: 500,
     ipc_version: 1,
 };
 
@@ -207,34 +586,288 @@ impl DownloadManager {
     pub fn new() -> Self {
         Self {
             tasks: Arc::new(Mutex::new(HashMap::new())),
-            max_concurrent: SYSTEM_GUARDRAILS.max_concurrent_downloads,
+            max_concurrent: AtomicUsize::new(SYSTEM_GUARDRAILS.max_concurrent_downloads),
+            host_limits: Mutex::new(HostLimits::default()),
+            bandwidth: Mutex::new(BandwidthLimits::default()),
+            schedule: Mutex::new(Schedule::default()),
+            pause_strategy: Mutex::new(PauseStrategy::default()),
+            disk_space: Mutex::new(DiskSpace::default()),
+            output_template: Mutex::new(None),
+            routing_rules: Mutex::new(Vec::new()),
+            playlist_streams: Mutex::new(HashSet::new()),
+            conflict_policy: Mutex::new(ConflictPolicy::default()),
+            archive_policy: Mutex::new(ArchivePolicy::default()),
+            playlists: Mutex::new(HashMap::new()),
+            playlist_emitted: Mutex::new(HashMap::new()),
+            space_held: Mutex::new(HashSet::new()),
+            schedule_changed: Notify::new(),
+            retry_policy: Mutex::new(RetryPolicy::default()),
+        }
+    }
+
+    /// Changes the slot count for subsequent scheduling. Lowering it never stops
+    /// running downloads; they drain and no new ones start until below the limit.
+    pub fn set_max_concurrent(&self, max: usize) -> Result<(), String> {
+        validate_max_concurrent(max)?;
+        let previous = self.max_concurrent.swap(max, Ordering::SeqCst);
+        log::info!("[QUEUE] Max concurrent downloads changed from {} to {}", previous, max);
+        Ok(())
+    }
+
+    pub fn set_host_limits(&self, limits: HostLimits) -> Result<(), String> {
+        limits.validate()?;
+        // Keys are matched against `url_host`, which is lowercase without `www.`
+        let overrides = limits
+            .overrides
+            .into_iter()
+            .map(|(host, limit)| {
+                let host = host.trim().to_lowercase();
+                (host.strip_prefix("www.").map(str::to_string).unwrap_or(host), limit)
+            })
+            .collect();
+        *self.host_limits.lock().unwrap() = HostLimits { per_host: limits.per_host, overrides };
+        Ok(())
+    }
+
+    /// Applies to pauses from now on; tasks that are already paused resume the way they were paused.
+    pub fn set_pause_strategy(&self, strategy: PauseStrategy) -> Result<(), String> {
+        strategy.validate()?;
+        *self.pause_strategy.lock().unwrap() = strategy;
+        Ok(())
+    }
+
+    /// Default template for tasks enqueued from now on; `None` keeps yt-dlp's naming.
+    pub fn set_output_template(&self, template: Option<String>) -> Result<(), String> {
+        let template = template.filter(|t| !t.trim().is_empty());
+        if let Some(t) = &template {
+            OutputTemplate::parse(t)?;
+        }
+        log::info!("[QUEUE] Output template changed to {:?}", template);
+        *self.output_template.lock().unwrap() = template;
+        Ok(())
+    }
+
+    pub fn set_routing_rules(&self, rules: Vec<RoutingRule>) -> Result<(), String> {
+        routing::validate_rules(&rules)?;
+        log::info!("[QUEUE] {} routing rules set", rules.len());
+        *self.routing_rules.lock().unwrap() = rules;
+        Ok(())
+    }
+
+    /// The folder the routing rules pick for a download, with the name of the
+    /// rule that matched. `None` when no rule applies.
+    pub fn route_output_dir<R: Runtime>(&self, app: &AppHandle<R>, url: &str, format_spec: Option<&str>, explicit_dir: Option<&str>, context: &RouteContext) -> Option<(String, std::path::PathBuf)> {
+        let input = RouteInput::new(url, format_spec, context);
+        let rules = self.routing_rules.lock().unwrap();
+        let rule = routing::route_download(&rules, &input, explicit_dir)?;
+        let home = app.path().home_dir().ok();
+        Some((rule.name.clone(), routing::expand_home(&rule.output_dir, home.as_deref())))
+    }
+
+    /// Points `options.output_dir` at the folder of the first matching rule.
+    /// An explicit folder always wins: rules only decide for downloads that
+    /// were started without one.
+    pub fn apply_routing<R: Runtime>(&self, app: &AppHandle<R>, url: &str, options: &mut DownloadOptions, context: &RouteContext) {
+        let explicit_dir = options.output_dir.as_deref();
+        if let Some((rule, dir)) = self.route_output_dir(app, url, options.format_spec.as_deref(), explicit_dir, context) {
+            log::info!("[QUEUE] Routing {} to {} (rule '{}')", url, dir.display(), rule);
+            options.output_dir = Some(dir.to_string_lossy().to_string());
+        }
+    }
+
+    pub fn set_retry_policy(&self, policy: RetryPolicy) -> Result<(), String> {
+        policy.validate()?;
+        log::info!("[QUEUE] Retry policy changed to {:?}", policy);
+        *self.retry_policy.lock().unwrap() = policy;
+        Ok(())
+    }
+
+    pub fn set_archive_policy(&self, policy: ArchivePolicy) {
+        log::info!("[ARCHIVE] Policy changed to {:?}", policy);
+        *self.archive_policy.lock().unwrap() = policy;
+    }
+
+    pub fn set_conflict_policy(&self, policy: ConflictPolicy) {
+        log::info!("[QUEUE] Conflict policy changed to {:?}", policy);
+        *self.conflict_policy.lock().unwrap() = policy;
+    }
+
+    pub fn set_disk_space(&self, disk_space: DiskSpace) -> Result<(), String> {
+        disk_space.validate()?;
+        log::info!("[QUEUE] Disk space guards changed to {:?}", disk_space);
+        *self.disk_space.lock().unwrap() = disk_space;
+        Ok(())
+    }
+
+    /// Pauses every download when a folder one of them writes to drops below
+    /// the free-space threshold, and retries held tasks otherwise.
+    pub fn check_disk_space<R: Runtime>(&self, app: &AppHandle<R>) {
+        let min_free = self.disk_space.lock().unwrap().min_free;
+        let dirs: HashSet<std::path::PathBuf> = {
+            let tasks = self.tasks.lock().unwrap();
+            tasks
+                .values()
+                .filter_map(|t| {
+                    let task = t.lock().unwrap();
+                    matches!(task.status, DownloadStatus::Downloading | DownloadStatus::Merging).then(|| output_dir(&task.options))
+                })
+                .collect()
+        };
+        let low = dirs
+            .into_iter()
+            .find_map(|dir| available_space(&dir).filter(|&free| free < min_free).map(|free| (dir, free)));
+
+        if let Some((dir, available)) = low {
+            log::warn!("[QUEUE] Only {} bytes free in {}, pausing all downloads", available, dir.display());
+            let results = self.pause_all(app);
+            let payload = DiskSpacePayload {
+                path: dir.to_string_lossy().to_string(),
+                available,
+                required: min_free,
+                task_ids: results.into_iter().filter(|r| r.ok).map(|r| r.id).collect(),
+                paused: true,
+            };
+            let _ = app.emit("disk-space-low", payload);
+        } else if !self.space_held.lock().unwrap().is_empty() {
+            self.process_queue(app.clone());
+        }
+    }
+
+    /// Runs `check_disk_space` periodically for the lifetime of the app.
+    pub fn spawn_disk_monitor<R: Runtime>(app: AppHandle<R>) {
+        tauri::async_runtime::spawn(async move {
+            let interval = std::time::Duration::from_secs(SYSTEM_GUARDRAILS.disk_check_interval_secs);
+            loop {
+                tokio::time::sleep(interval).await;
+                app.state::<DownloadManager>().check_disk_space(&app);
+            }
+        });
+    }
+
+    pub fn set_bandwidth_limits(&self, limits: BandwidthLimits) -> Result<(), String> {
+        limits.validate()?;
+        log::info!("[QUEUE] Bandwidth limits changed to {:?}", limits);
+        *self.bandwidth.lock().unwrap() = limits;
+        Ok(())
+    }
+
+    pub fn set_schedule(&self, schedule: Schedule) -> Result<(), String> {
+        schedule.validate()?;
+        log::info!("[SCHEDULE] Schedule updated: enabled={}, {} windows", schedule.enabled, schedule.windows.len());
+        *self.schedule.lock().unwrap() = schedule;
+        self.schedule_changed.notify_one();
+        Ok(())
+    }
+
+    /// The open window's bandwidth override, or the global limits.
+    fn effective_bandwidth(&self) -> BandwidthLimits {
+        let schedule = self.schedule.lock().unwrap();
+        let window_limits = schedule
+            .enabled
+            .then(|| schedule.active_window(chrono::Local::now().naive_local()))
+            .flatten()
+            .and_then(|w| w.bandwidth.clone());
+        window_limits.unwrap_or_else(|| self.bandwidth.lock().unwrap().clone())
+    }
+
+    /// Re-runs the queue and bandwidth split whenever a window opens or closes.
+    /// Runs for the lifetime of the app.
+    pub fn spawn_schedule_timer<R: Runtime>(app: AppHandle<R>) {
+        tauri::async_runtime::spawn(async move {
+            loop {
+                let wait = {
+                    let manager = app.state::<DownloadManager>();
+                    let schedule = manager.schedule.lock().unwrap();
+                    schedule.until_next_boundary(chrono::Local::now())
+                };
+                let manager = app.state::<DownloadManager>();
+                match wait {
+                    Some(wait) => {
+                        tokio::select! {
+                            _ = tokio::time::sleep(wait) => {}
+                            _ = manager.schedule_changed.notified() => {}
+                        }
+                    }
+                    None => manager.schedule_changed.notified().await,
+                }
+                log::info!("[SCHEDULE] Re-evaluating queue");
+                manager.process_queue(app.clone());
+                manager.rebalance_bandwidth();
+            }
+        });
+    }
+
+    /// Lets a queued task bypass the schedule and moves it to the front.
+    pub fn start_now(&self, id: &str) -> Result<Vec<String>, String> {
+        {
+            let tasks = self.tasks.lock().unwrap();
+            let mut task = tasks.get(id).ok_or_else(|| "Task not found".to_string())?.lock().unwrap();
+            if task.status != DownloadStatus::Queued {
+                return Err("Only queued tasks can be started now".to_string());
+            }
+            task.options.ignore_schedule = true;
+        }
+        let mut changed = self.reorder_download(id, 0)?;
+        if !changed.iter().any(|c| c == id) {
+            changed.push(id.to_string());
+        }
+        Ok(changed)
+    }
+
+    /// Rate for one of `active` running tasks: an equal split of the global
+    /// limit, narrowed by the per-task caps. `None` means unlimited.
+    fn rate_share(&self, active: usize, options: &DownloadOptions) -> Option<u64> {
+        let limits = self.effective_bandwidth();
+        let share = limits.global.map(|g| g / active.max(1) as u64);
+        [share, limits.per_task, options.rate_limit]
+            .into_iter()
+            .flatten()
+            .min()
+            .map(|rate| rate.max(SYSTEM_GUARDRAILS.min_rate_limit))
+    }
+
+    /// Restarts running downloads whose `--limit-rate` is more than 20% away
+    /// from their current share. yt-dlp cannot change its rate in flight, so
+    /// the process is killed and re-queued at the front; `--continue` picks up
+    /// the partial file.
+    pub fn rebalance_bandwidth(&self) {
+        let active: Vec<_> = {
+            let tasks = self.tasks.lock().unwrap();
+            tasks
+                .values()
+                .filter(|t| matches!(t.lock().unwrap().status, DownloadStatus::Preparing | DownloadStatus::Downloading | DownloadStatus::Merging))
+                .cloned()
+                .collect()
+        };
+
+        for task_ref in &active {
+            let mut task = task_ref.lock().unwrap();
+            if task.status != DownloadStatus::Downloading || task.stop_reason.is_some() {
+                continue;
+            }
+            let target = self.rate_share(active.len(), &task.options);
+            let off = match (task.rate_limit, target) {
+                (None, None) => false,
+                (Some(current), Some(target)) => current.abs_diff(target) * 5 > target,
+                _ => true,
+            };
+            if off {
+                if let Some(child) = task.child.take() {
+                    log::info!("[QUEUE] Restarting {} to apply rate {:?} (was {:?})", task.id, target, task.rate_limit);
+                    task.stop_reason = Some(StopReason::Restart);
+                    child.terminate();
+                }
+            }
         }
     }
 
     pub async fn get_video_metadata<R: Runtime>(&self, _app: AppHandle<R>, url: String) -> Result<VideoMetadata, String> {
         log::info!("[METADATA] Starting analysis for URL: {}", url);
         let max_items = SYSTEM_GUARDRAILS.max_playlist_items.to_string();
-        
-        // Try to find yt-dlp directly from the executable's directory (works in both dev and production)
-        let exe_path = std::env::current_exe()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_else(|e| format!("error: {}", e));
-        log::info!("[METADATA] Executable path: {}", exe_path);
-        
-        let yt_dlp_path = std::env::current_exe()
-            .ok()
-            .and_then(|p| p.parent().map(|p| p.join("yt-dlp").to_string_lossy().to_string()))
-            .unwrap_or_else(|| "yt-dlp".to_string());
-        
+
+        let yt_dlp_path = yt_dlp_path();
         log::info!("[METADATA] Using yt-dlp at: {}", yt_dlp_path);
         
-        // Check if yt-dlp exists
-        if std::path::Path::new(&yt_dlp_path).exists() {
-            log::info!("[METADATA] yt-dlp EXISTS at path");
-        } else {
-            log::warn!("[METADATA] yt-dlp NOT FOUND at path, will search in PATH");
-        }
-        
         let mut cmd = tokio::process::Command::new(&yt_dlp_path);
         cmd.args(["-J", "--flat-playlist", "--no-warnings", "--playlist-end", &max_items, &url]);
 
@@ -277,16 +910,7 @@ impl DownloadManager {
         let mut entries = Vec::new();
 
         if is_playlist {
-            if let Some(arr) = v["entries"].as_array() {
-                for entry in arr {
-                    entries.push(PlaylistEntry {
-                        id: entry["id"].as_str().unwrap_or_default().to_string(),
-                        title: entry["title"].as_str().unwrap_or_default().to_string(),
-                        url: entry["url"].as_str().unwrap_or_default().to_string(),
-                        duration: entry["duration"].as_f64(),
-                    });
-                }
-            }
+            entries = parse_entries(&v, 0);
         } else {
             if let Some(arr) = v["formats"].as_array() {
                 for f in arr {
@@ -318,6 +942,9 @@ impl DownloadManager {
         }
 
         let webpage_url = v["webpage_url"].as_str().unwrap_or(&url).to_string();
+        let archive_id = if is_playlist { None } else { v["extractor_key"].as_str().and_then(|ie| archive::archive_id(ie, &id)) };
+        let total_entries = v["playlist_count"].as_u64();
+        let has_more = is_playlist && has_more_entries(0, SYSTEM_GUARDRAILS.max_playlist_items, entries.len(), total_entries);
 
         Ok(VideoMetadata {
             id,
@@ -328,61 +955,152 @@ impl DownloadManager {
             formats,
             is_playlist,
             entries: if is_playlist { Some(entries) } else { None },
+            uploader: v["uploader"].as_str().map(str::to_string),
+            channel: v["channel"].as_str().map(str::to_string),
+            upload_date: v["upload_date"].as_str().map(str::to_string),
+            extractor: v["extractor"].as_str().map(str::to_string),
+            archive_id,
+            total_entries,
+            has_more,
         })
     }
 
-    pub fn cancel_download(&self, id: &str) -> bool {
-        let tasks = self.tasks.lock().unwrap();
-        if let Some(task_arc) = tasks.get(id) {
-            let mut task = task_arc.lock().unwrap();
-            if let Some(child) = task.child.take() {
-                #[cfg(windows)]
-                {
-                    let pid = child.pid();
-                    let _ = std::process::Command::new("taskkill")
-                        .args(["/F", "/T", "/PID", &pid.to_string()])
-                        .spawn();
+    /// Lists `limit` playlist entries starting after the first `offset`. At most
+    /// `max_playlist_items` are fetched at once, so a page stays small however
+    /// large the playlist is.
+    pub async fn get_playlist_page(url: &str, offset: u32, limit: u32) -> Result<PlaylistPage, String> {
+        if limit == 0 || limit > SYSTEM_GUARDRAILS.max_playlist_items {
+            return Err(format!("limit must be between 1 and {}", SYSTEM_GUARDRAILS.max_playlist_items));
+        }
+        let end = offset
+            .checked_add(limit)
+            .ok_or_else(|| format!("Playlist offset {} is out of range", offset))?;
+        // offset + 1 <= offset + limit, which was checked above
+        let (start, end) = ((offset + 1).to_string(), end.to_string());
+
+        let mut cmd = tokio::process::Command::new(yt_dlp_path());
+        cmd.args(["-J", "--flat-playlist", "--no-warnings", "--playlist-start", &start, "--playlist-end", &end, url]);
+        #[cfg(windows)]
+        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+        let output = cmd.output().await.map_err(|e| format!("Failed to execute yt-dlp: {}", e))?;
+        if !output.status.success() {
+            return Err(format!("yt-dlp failed: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+        let v: serde_json::Value = serde_json::from_slice(&output.stdout).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+        if v["_type"].as_str() != Some("playlist") {
+            return Err("URL is not a playlist".to_string());
+        }
+
+        let entries = parse_entries(&v, offset);
+        let total = v["playlist_count"].as_u64();
+        log::info!("[METADATA] Playlist page {}-{} of {}: {} entries", start, end, url, entries.len());
+        Ok(PlaylistPage {
+            url: url.to_string(),
+            offset,
+            has_more: has_more_entries(offset, limit, entries.len(), total),
+            entries,
+            total,
+        })
+    }
+
+    /// Fetches a playlist page by page from `offset`, emitting each as
+    /// `playlist-page`, until the end or `cancel_playlist_stream`. Only one
+    /// page is held at a time. Returns the stream id.
+    pub fn stream_playlist<R: Runtime>(&self, app: AppHandle<R>, url: String, offset: u32, page_size: u32) -> String {
+        let stream_id = uuid::Uuid::new_v4().to_string();
+        self.playlist_streams.lock().unwrap().insert(stream_id.clone());
+
+        let id = stream_id.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut offset = offset;
+            let streaming = || app.state::<DownloadManager>().playlist_streams.lock().unwrap().contains(&id);
+            while streaming() {
+                let payload = match Self::get_playlist_page(&url, offset, page_size).await {
+                    Ok(page) => {
+                        offset = offset.saturating_add(page.entries.len() as u32);
+                        PlaylistPagePayload { stream_id: id.clone(), done: !page.has_more, page: Some(page), error: None }
+                    }
+                    Err(e) => {
+                        log::warn!("[METADATA] Playlist stream {} failed at offset {}: {}", id, offset, e);
+                        PlaylistPagePayload { stream_id: id.clone(), done: true, page: None, error: Some(e) }
+                    }
+                };
+                let done = payload.done;
+                // A page that arrives after cancelling is dropped
+                if streaming() {
+                    let _ = app.emit("playlist-page", payload);
                 }
-                #[cfg(not(windows))]
-                {
-                    let _ = child.kill();
+                if done {
+                    break;
                 }
             }
-            let _ = task.transition(DownloadStatus::Cancelled);
+            app.state::<DownloadManager>().playlist_streams.lock().unwrap().remove(&id);
+        });
+        stream_id
+    }
+
+    pub fn cancel_playlist_stream(&self, stream_id: &str) -> bool {
+        self.playlist_streams.lock().unwrap().remove(stream_id)
+    }
+
+    /// Marks the task cancelled. A task with a live process group is only
+    /// asked to stop here; it becomes Cancelled when the group has exited.
+    fn cancel_task(task: &mut DownloadTask) -> bool {
+        if !task.status.can_transition_to(&DownloadStatus::Cancelled) {
+            return false;
+        }
+        if let Some(child) = task.child.take() {
+            task.stop_reason = Some(StopReason::Cancel);
+            child.terminate();
+            return true;
+        }
+        if task.stop_reason.is_some() {
+            // Already being stopped for a pause or restart; settle as cancelled instead
+            task.stop_reason = Some(StopReason::Cancel);
+            return true;
+        }
+        if task.status == DownloadStatus::Paused {
+            // A kill-and-continue pause has no process left to clean up after it
+            task.remove_artifacts(cleanup::Cleanup::Everything);
+            task.restore_versioned();
+        }
+        if task.transition(DownloadStatus::Cancelled) {
+            task.error = Some(DownloadError::Cancelled);
             true
         } else {
             false
         }
     }
 
-    pub fn pause_download(&self, id: &str) -> bool {
-        let tasks = self.tasks.lock().unwrap();
-        if let Some(task_arc) = tasks.get(id) {
-            let mut task = task_arc.lock().unwrap();
-            
-            // Only pause if downloading
-            if task.status != DownloadStatus::Downloading {
+    fn pause_task(task: &mut DownloadTask, strategy: PauseStrategy) -> bool {
+        // Only pause if downloading
+        if task.status != DownloadStatus::Downloading {
+            return false;
+        }
+
+        if strategy == PauseStrategy::Restart {
+            // End the process but keep its .part/.ytdl files for the relaunch
+            let Some(child) = task.child.take() else {
                 return false;
-            }
+            };
+            task.stop_reason = Some(StopReason::Pause);
+            child.terminate();
+            return task.transition(DownloadStatus::Paused);
+        }
 
-            if let Some(child) = &task.child {
-                #[cfg(unix)]
-                {
-                    let pid = child.pid();
-                    let _ = std::process::Command::new("kill")
-                        .args(["-STOP", &pid.to_string()])
-                        .spawn();
-                    let _ = task.transition(DownloadStatus::Paused);
-                    return true;
-                }
-                #[cfg(windows)]
-                {
-                    // Generic Windows pause is hard without specialized APIs, 
-                    // we could kill but yt-dlp resume is handled by restarting.
-                    // For now we just return false or implement tree-suspend.
-                    false
-                }
-            } else {
+        if let Some(child) = &task.child {
+            #[cfg(unix)]
+            {
+                child.suspend();
+                let _ = task.transition(DownloadStatus::Paused);
+                true
+            }
+            #[cfg(windows)]
+            {
+                // Generic Windows pause is hard without specialized APIs, 
+                // we could kill but yt-dlp resume is handled by restarting.
+                // For now we just return false or implement tree-suspend.
                 false
             }
         } else {
@@ -390,141 +1108,876 @@ impl DownloadManager {
         }
     }
 
-    pub fn resume_download(&self, id: &str) -> bool {
-        let tasks = self.tasks.lock().unwrap();
-        if let Some(task_arc) = tasks.get(id) {
-            let mut task = task_arc.lock().unwrap();
-            
-            if task.status != DownloadStatus::Paused {
-                return false;
+    /// Continues a suspended process in place, or moves a task without a
+    /// process (kill-and-continue pause, or paused before a restart) back to
+    /// Queued. The caller is responsible for queue placement.
+    fn resume_task(task: &mut DownloadTask) -> Option<DownloadStatus> {
+        if task.status != DownloadStatus::Paused {
+            return None;
+        }
+
+        if let Some(child) = &task.child {
+            #[cfg(unix)]
+            {
+                child.resume();
+                let _ = task.transition(DownloadStatus::Downloading);
+                return Some(DownloadStatus::Downloading);
+            }
+            #[cfg(windows)]
+            {
+                let _ = child;
+                return None;
             }
+        }
 
-            if let Some(child) = &task.child {
-                #[cfg(unix)]
-                {
-                    let pid = child.pid();
-                    let _ = std::process::Command::new("kill")
-                        .args(["-CONT", &pid.to_string()])
-                        .spawn();
-                    let _ = task.transition(DownloadStatus::Downloading);
-                    return true;
-                }
-                #[cfg(windows)]
-                {
-                    false
+        // The killed process has not reported back yet
+        if task.stop_reason.is_some() || !task.requeue_stopped() {
+            return None;
+        }
+        Some(DownloadStatus::Queued)
+    }
+
+    /// Moves a failed task back to Queued as a fresh attempt. A manual retry
+    /// starts a new round of automatic retries, so `retry_count` goes back to
+    /// zero. The caller is responsible for queue placement.
+    fn retry_task(task: &mut DownloadTask) -> bool {
+        if !task.transition(DownloadStatus::Queued) {
+            return false;
+        }
+        task.retry_count = 0;
+        task.next_attempt_at = None;
+        task.error = None;
+        task.speed = None;
+        task.eta = None;
+        log::info!("[DOWNLOAD] Retrying {} by request", task.id);
+        true
+    }
+
+    pub fn cancel_download(&self, id: &str) -> bool {
+        let cancelled = match self.tasks.lock().unwrap().get(id) {
+            Some(task_arc) => Self::cancel_task(&mut task_arc.lock().unwrap()),
+            None => false,
+        };
+        if cancelled {
+            self.release_space(id);
+        }
+        cancelled
+    }
+
+    /// Drops the free-space hold on a task that has left the queue, so the
+    /// held set only tracks tasks still waiting for room.
+    fn release_space(&self, id: &str) {
+        self.space_held.lock().unwrap().remove(id);
+    }
+
+    /// Waits until the task's process group has exited and the task has
+    /// settled, giving up after the kill grace period plus a margin.
+    pub async fn wait_for_exit(&self, id: &str) {
+        let deadline = Instant::now() + std::time::Duration::from_millis(SYSTEM_GUARDRAILS.kill_grace_ms + 2000);
+        while Instant::now() < deadline {
+            let stopping = match self.tasks.lock().unwrap().get(id) {
+                Some(task) => {
+                    let task = task.lock().unwrap();
+                    task.child.is_some() || task.stop_reason.is_some()
                 }
-            } else {
-                false
+                None => false,
+            };
+            if !stopping {
+                return;
             }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        log::warn!("[DOWNLOAD] {} still stopping after the grace period", id);
+    }
+
+    pub fn pause_download(&self, id: &str) -> bool {
+        let strategy = *self.pause_strategy.lock().unwrap();
+        let tasks = self.tasks.lock().unwrap();
+        if let Some(task_arc) = tasks.get(id) {
+            Self::pause_task(&mut task_arc.lock().unwrap(), strategy)
         } else {
             false
         }
     }
 
-    pub fn cleanup_all(&self) {
+    /// Returns the status the task moved to. A task that was re-queued goes to
+    /// the front of the queue.
+    pub fn resume_download<R: Runtime>(&self, app: AppHandle<R>, id: &str) -> Option<DownloadStatus> {
+        let task_ref = self.tasks.lock().unwrap().get(id).cloned()?;
+        let status = Self::resume_task(&mut task_ref.lock().unwrap())?;
+        if status == DownloadStatus::Queued {
+            let changed = self.reorder_download(id, 0).unwrap_or_default();
+            self.persist_tasks(&app, &changed);
+            self.process_queue(app);
+        }
+        Some(status)
+    }
+
+    pub fn retry_download<R: Runtime>(&self, app: AppHandle<R>, id: &str) -> bool {
+        let task_ref = {
+            let tasks = self.tasks.lock().unwrap();
+            match tasks.get(id) {
+                Some(t) => t.clone(),
+                None => return false,
+            }
+        };
+
+        let payload = {
+            let mut task = task_ref.lock().unwrap();
+            if !Self::retry_task(&mut task) {
+                return false;
+            }
+            task.to_payload()
+        };
+        let _ = app.emit("download-progress", payload);
+        let mut changed = self.enqueue(id);
+        if !changed.iter().any(|c| c == id) {
+            changed.push(id.to_string());
+        }
+        self.persist_tasks(&app, &changed);
+
+        // The task keeps its original options, so the queue restarts it exactly as submitted
+        self.process_queue(app);
+        true
+    }
+
+    /// Applies `op` to every task matching `select` while holding the task map,
+    /// so no task can start, finish or be added halfway through.
+    fn bulk_apply(
+        &self,
+        select: impl Fn(&DownloadTask) -> bool,
+        mut op: impl FnMut(&mut DownloadTask) -> bool,
+    ) -> Vec<BulkResult> {
         let tasks = self.tasks.lock().unwrap();
-        for (_, task_arc) in tasks.iter() {
-            let mut task = task_arc.lock().unwrap();
-            if let Some(child) = task.child.take() {
-                #[cfg(windows)]
-                {
-                    let pid = child.pid();
-                    let _ = std::process::Command::new("taskkill")
-                        .args(["/F", "/T", "/PID", &pid.to_string()])
-                        .spawn();
+        let mut selected: Vec<_> = tasks.values().filter(|t| select(&t.lock().unwrap())).collect();
+        selected.sort_by_cached_key(|t| t.lock().unwrap().queue_key());
+        selected
+            .into_iter()
+            .map(|task_ref| {
+                let mut task = task_ref.lock().unwrap();
+                let ok = op(&mut task);
+                BulkResult { id: task.id.clone(), ok, status: task.status.clone() }
+            })
+            .collect()
+    }
+
+    /// Persists and reports every task a bulk operation changed, then lets the
+    /// queue and bandwidth split catch up.
+    fn finish_bulk<R: Runtime>(&self, app: &AppHandle<R>, results: &[BulkResult]) {
+        let changed: Vec<String> = results.iter().filter(|r| r.ok).map(|r| r.id.clone()).collect();
+        self.persist_tasks(app, &changed);
+        let task_refs: Vec<_> = {
+            let tasks = self.tasks.lock().unwrap();
+            changed.iter().filter_map(|id| tasks.get(id).cloned()).collect()
+        };
+        for task_ref in task_refs {
+            let payload = task_ref.lock().unwrap().to_payload();
+            let _ = app.emit("download-progress", payload);
+        }
+        self.process_queue(app.clone());
+        self.rebalance_bandwidth();
+    }
+
+    pub fn pause_all<R: Runtime>(&self, app: &AppHandle<R>) -> Vec<BulkResult> {
+        let strategy = *self.pause_strategy.lock().unwrap();
+        let results = self.bulk_apply(
+            |t| t.status == DownloadStatus::Downloading,
+            |t| Self::pause_task(t, strategy),
+        );
+        log::info!("[QUEUE] Paused {} of {} downloads", results.iter().filter(|r| r.ok).count(), results.len());
+        self.finish_bulk(app, &results);
+        results
+    }
+
+    pub fn resume_all<R: Runtime>(&self, app: &AppHandle<R>) -> Vec<BulkResult> {
+        let results = self.bulk_apply(
+            |t| t.status == DownloadStatus::Paused,
+            |t| Self::resume_task(t).is_some(),
+        );
+        // Re-queued tasks go ahead of other waiting work, keeping their relative order
+        let mut changed = Vec::new();
+        let requeued = results.iter().filter(|r| r.ok && r.status == DownloadStatus::Queued);
+        for (index, result) in requeued.enumerate() {
+            changed.extend(self.reorder_download(&result.id, index).unwrap_or_default());
+        }
+        self.persist_tasks(app, &changed);
+        log::info!("[QUEUE] Resumed {} of {} downloads", results.iter().filter(|r| r.ok).count(), results.len());
+        self.finish_bulk(app, &results);
+        results
+    }
+
+    pub fn cancel_queued<R: Runtime>(&self, app: &AppHandle<R>) -> Vec<BulkResult> {
+        let results = self.bulk_apply(|t| t.status == DownloadStatus::Queued, Self::cancel_task);
+        results.iter().filter(|r| r.ok).for_each(|r| self.release_space(&r.id));
+        log::info!("[QUEUE] Cancelled {} queued downloads", results.iter().filter(|r| r.ok).count());
+        self.finish_bulk(app, &results);
+        results
+    }
+
+    pub fn retry_failed<R: Runtime>(&self, app: &AppHandle<R>) -> Vec<BulkResult> {
+        let results = self.bulk_apply(|t| t.status == DownloadStatus::Error, Self::retry_task);
+        let mut changed = Vec::new();
+        for result in results.iter().filter(|r| r.ok) {
+            changed.extend(self.enqueue(&result.id));
+        }
+        self.persist_tasks(app, &changed);
+        self.finish_bulk(app, &results);
+        results
+    }
+
+    fn in_group(task: &DownloadTask, group_id: &str) -> bool {
+        task.options.playlist.as_ref().is_some_and(|p| p.group_id == group_id)
+    }
+
+    pub fn cancel_playlist<R: Runtime>(&self, app: &AppHandle<R>, group_id: &str) -> Vec<BulkResult> {
+        let results = self.bulk_apply(|t| Self::in_group(t, group_id), Self::cancel_task);
+        results.iter().filter(|r| r.ok).for_each(|r| self.release_space(&r.id));
+        log::info!("[PLAYLIST] Cancelled {} entries of {}", results.iter().filter(|r| r.ok).count(), group_id);
+        self.finish_bulk(app, &results);
+        results
+    }
+
+    /// Re-queues the failed entries of a playlist.
+    pub fn retry_playlist<R: Runtime>(&self, app: &AppHandle<R>, group_id: &str) -> Vec<BulkResult> {
+        let results = self.bulk_apply(
+            |t| Self::in_group(t, group_id) && t.status == DownloadStatus::Error,
+            Self::retry_task,
+        );
+        let mut changed = Vec::new();
+        for result in results.iter().filter(|r| r.ok) {
+            changed.extend(self.enqueue(&result.id));
+        }
+        self.persist_tasks(app, &changed);
+        self.finish_bulk(app, &results);
+        results
+    }
+
+    /// Drops completed tasks from the download list. Their records stay in the
+    /// store, archived, so they remain in the history but are not reloaded.
+    pub fn clear_completed<R: Runtime>(&self, app: &AppHandle<R>) -> Vec<BulkResult> {
+        let removed: Vec<_> = {
+            let mut tasks = self.tasks.lock().unwrap();
+            let ids: Vec<String> = tasks
+                .iter()
+                .filter(|(_, t)| t.lock().unwrap().status == DownloadStatus::Completed)
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.iter().filter_map(|id| tasks.remove(id)).collect()
+        };
+
+        if let Some(persistence) = app.try_state::<crate::persistence::PersistenceManager>() {
+            let records: Vec<_> = removed
+                .iter()
+                .map(|t| {
+                    let mut record = crate::persistence::PersistedTask::from_task(&t.lock().unwrap());
+                    record.archived = true;
+                    record
+                })
+                .collect();
+            if let Err(e) = persistence.save_tasks(&records) {
+                log::error!("[PERSISTENCE] Failed to archive completed tasks: {}", e);
+            }
+        }
+
+        log::info!("[QUEUE] Cleared {} completed downloads", removed.len());
+        let results: Vec<BulkResult> = removed
+            .iter()
+            .map(|t| {
+                let task = t.lock().unwrap();
+                BulkResult { id: task.id.clone(), ok: true, status: task.status.clone() }
+            })
+            .collect();
+        // Progress events cannot express removal, so tell the frontend which entries to drop
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        let _ = app.emit("downloads-cleared", ids);
+        results
+    }
+
+    /// Deletes tasks from the list and the store, optionally with their files.
+    /// Tasks that still have a process (running, suspended or shutting down)
+    /// are refused and reported with `ok: false`.
+    pub fn remove_downloads<R: Runtime>(&self, app: &AppHandle<R>, ids: &[String], delete_files: bool) -> Vec<BulkResult> {
+        let persistence = app.try_state::<crate::persistence::PersistenceManager>();
+        // Records only the store still has, e.g. after "clear completed"
+        let missing: Vec<String> = {
+            let tasks = self.tasks.lock().unwrap();
+            ids.iter().filter(|id| !tasks.contains_key(*id)).cloned().collect()
+        };
+        let stored: HashMap<String, crate::persistence::PersistedTask> = match &persistence {
+            Some(p) if !missing.is_empty() => p.load_tasks_by_id(&missing).unwrap_or_default(),
+            _ => Vec::new(),
+        }
+        .into_iter()
+        .map(|t| (t.id.clone(), t))
+        .collect();
+
+        let mut results = Vec::new();
+        let mut media = Vec::new();
+        let mut dropped = Vec::new();
+        {
+            let mut tasks = self.tasks.lock().unwrap();
+            for id in ids {
+                if let Some(task_ref) = tasks.get(id).cloned() {
+                    let task = task_ref.lock().unwrap();
+                    let busy = task.child.is_some()
+                        || task.stop_reason.is_some()
+                        || matches!(task.status, DownloadStatus::Preparing | DownloadStatus::Downloading | DownloadStatus::Merging);
+                    results.push(BulkResult { id: id.clone(), ok: !busy, status: task.status.clone() });
+                    if busy {
+                        continue;
+                    }
+                    media.extend(task.final_path.clone());
+                    drop(task);
+                    dropped.extend(tasks.remove(id));
+                } else if let Some(record) = stored.get(id) {
+                    results.push(BulkResult { id: id.clone(), ok: true, status: record.status.clone() });
+                    media.extend(record.download_dir.as_ref().map(std::path::PathBuf::from));
+                }
+            }
+        }
+
+        let removed: Vec<String> = results.iter().filter(|r| r.ok).map(|r| r.id.clone()).collect();
+        removed.iter().for_each(|id| self.release_space(id));
+        if let Some(persistence) = &persistence {
+            if let Err(e) = persistence.delete_tasks(&removed) {
+                log::error!("[PERSISTENCE] Failed to delete {} tasks: {}", removed.len(), e);
+            }
+        }
+        // Fragments a failed download kept for a retry are of no use once the task is gone
+        let cleanup = if delete_files { cleanup::Cleanup::Everything } else { cleanup::Cleanup::Partials };
+        for task_ref in dropped {
+            task_ref.lock().unwrap().remove_artifacts(cleanup);
+        }
+        if delete_files {
+            for path in &media {
+                cleanup::delete_download_files(path);
+            }
+        }
+
+        log::info!("[QUEUE] Removed {} of {} tasks (files deleted: {})", removed.len(), ids.len(), delete_files);
+        let _ = app.emit("downloads-cleared", &removed);
+        results
+    }
+
+    /// Looks for `.part`/`.ytdl`/`.temp` fragments left next to known
+    /// downloads by tasks that cannot be resumed any more, e.g. after a crash.
+    pub fn scan_orphaned_files<R: Runtime>(&self, app: &AppHandle<R>) -> cleanup::SweepReport {
+        let policy = self.retry_policy.lock().unwrap().clone();
+        let mut targets: HashMap<String, cleanup::SweepTarget> = HashMap::new();
+        if let Some(persistence) = app.try_state::<crate::persistence::PersistenceManager>() {
+            for record in persistence.load_tasks().unwrap_or_default() {
+                if let Some(path) = record.download_dir {
+                    let resumable = cleanup::is_resumable(&record.status, record.error.as_ref(), &policy);
+                    let target = cleanup::SweepTarget { task_id: record.id.clone(), media_path: path.into(), resumable };
+                    targets.insert(record.id, target);
                 }
-                #[cfg(not(windows))]
-                {
-                    let _ = child.kill();
+            }
+        }
+        // The live list is more current than the store
+        for task_ref in self.tasks.lock().unwrap().values() {
+            let task = task_ref.lock().unwrap();
+            let resumable = cleanup::is_resumable(&task.status, task.error.as_ref(), &policy)
+                || task.child.is_some()
+                || task.stop_reason.is_some();
+            match (&task.final_path, targets.get_mut(&task.id)) {
+                (Some(path), Some(target)) => {
+                    target.media_path = path.clone();
+                    target.resumable = resumable;
                 }
+                (None, Some(target)) => target.resumable = resumable,
+                (Some(path), None) => {
+                    let target = cleanup::SweepTarget { task_id: task.id.clone(), media_path: path.clone(), resumable };
+                    targets.insert(task.id.clone(), target);
+                }
+                (None, None) => {}
             }
         }
+
+        let targets: Vec<cleanup::SweepTarget> = targets.into_values().collect();
+        let report = cleanup::find_orphans(&targets);
+        log::info!("[CLEANUP] Found {} orphaned files ({} bytes) across {} known downloads", report.files.len(), report.reclaimable_bytes, targets.len());
+        report
+    }
+
+    /// Deletes the confirmed paths from a sweep. Paths are checked against a
+    /// fresh scan, so only files that are still orphaned are removed.
+    pub fn delete_orphaned_files<R: Runtime>(&self, app: &AppHandle<R>, paths: &[String]) -> cleanup::SweepReport {
+        let confirmed = self
+            .scan_orphaned_files(app)
+            .files
+            .into_iter()
+            .filter(|f| paths.contains(&f.path))
+            .collect();
+        cleanup::delete_orphans(confirmed)
+    }
+
+    /// Stops every running process group and waits for them to exit, so
+    /// nothing keeps writing into the download folders after the app quits.
+    pub fn cleanup_all(&self) {
+        let children: Vec<ProcessHandle> = {
+            let tasks = self.tasks.lock().unwrap();
+            tasks.values().filter_map(|t| t.lock().unwrap().child.take()).collect()
+        };
+        let stopping: Vec<_> = children
+            .into_iter()
+            .map(|child| std::thread::spawn(move || child.terminate_and_wait()))
+            .collect();
+        for handle in stopping {
+            let _ = handle.join();
+        }
     }
 
     pub fn get_tasks(&self) -> Vec<DownloadProgressPayload> {
         let tasks = self.tasks.lock().unwrap();
-        tasks.values().map(|t| {
-            let task = t.lock().unwrap();
-            DownloadProgressPayload {
-                id: task.id.clone(),
-                progress: task.progress,
-                speed: task.speed,
-                eta: task.eta,
-                status: task.status.clone(),
-                total_size: task.total_size,
-                downloaded_bytes: task.downloaded_bytes,
-                can_retry: Some(task.status == DownloadStatus::Error),
-                error_message: None,
-                final_path: task.final_path.as_ref().map(|p| p.to_string_lossy().to_string()),
-                version: SYSTEM_GUARDRAILS.ipc_version,
-            }
-        }).collect()
-    }
-
-    pub fn start_download<R: Runtime>(&self, app: AppHandle<R>, url: String, id: String, title: String, path: Option<String>, format_spec: Option<String>, cookies: Option<String>) {
-        let _tasks_clone = self.tasks.clone();
-        
+        tasks.values().map(|t| t.lock().unwrap().to_payload()).collect()
+    }
+
+    pub fn start_download<R: Runtime>(&self, app: AppHandle<R>, url: String, id: String, title: String, options: DownloadOptions, priority: Priority) -> Result<(), String> {
+        let task = self.build_task(&app, url, id.clone(), title, options, priority)?;
         {
             let mut map = self.tasks.lock().unwrap();
             if map.contains_key(&id) {
-                return; // Already exists
+                return Ok(()); // Already exists
             }
-            map.insert(id.clone(), Arc::new(Mutex::new(DownloadTask::new(id.clone(), url.clone(), title))));
+            map.insert(id.clone(), Arc::new(Mutex::new(task)));
         }
 
-        let _app_clone = app.clone();
-        let _download_manager = app.state::<DownloadManager>();
-        
+        // Persist before starting so a queued task survives a crash
+        self.queue_new(&app, &[id]);
+
         // Signal the queue to process
-        self.process_queue(app, path, format_spec, cookies);
+        self.process_queue(app);
+        Ok(())
+    }
+
+    /// Checks a new download against the archive and fills in the settings'
+    /// defaults. The task is not in the task map or the queue yet.
+    fn build_task<R: Runtime>(&self, app: &AppHandle<R>, url: String, id: String, title: String, mut options: DownloadOptions, priority: Priority) -> Result<DownloadTask, String> {
+        if let Some(archive_id) = options.archive_id.as_deref().filter(|_| !options.ignore_archive) {
+            let archived = app
+                .try_state::<crate::persistence::PersistenceManager>()
+                .is_some_and(|p| p.archive().contains(archive_id));
+            match *self.archive_policy.lock().unwrap() {
+                ArchivePolicy::Skip if archived => {
+                    log::info!("[ARCHIVE] Skipping {} ({}), already downloaded", url, archive_id);
+                    return Err(format!("\"{}\" has already been downloaded", title));
+                }
+                ArchivePolicy::Warn if archived => {
+                    log::info!("[ARCHIVE] {} ({}) was downloaded before, downloading again", url, archive_id);
+                    let _ = app.emit("already-downloaded", ArchiveMatchPayload {
+                        id: id.clone(),
+                        title: title.clone(),
+                        archive_id: archive_id.to_string(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        if options.output_template.is_none() {
+            options.output_template = self.output_template.lock().unwrap().clone();
+        }
+        if options.conflict_policy.is_none() {
+            options.conflict_policy = Some(*self.conflict_policy.lock().unwrap());
+        }
+        let mut task = DownloadTask::new(id, url, title, options);
+        task.priority = priority;
+        Ok(task)
+    }
+
+    /// Queues tasks that were just added to the task map and saves them,
+    /// together with every task whose position moved, in one write.
+    fn queue_new<R: Runtime>(&self, app: &AppHandle<R>, ids: &[String]) {
+        let mut changed: HashSet<String> = self.enqueue_all(ids).into_iter().collect();
+        changed.extend(ids.iter().cloned());
+        self.persist_tasks(app, &changed.into_iter().collect::<Vec<_>>());
+    }
+
+    /// Creates a group for the selected playlist entries and enqueues one child
+    /// task per entry, in playlist order. Each child is an ordinary task that
+    /// can be paused, cancelled or retried on its own.
+    pub fn enqueue_playlist<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        request: PlaylistRequest,
+        options: DownloadOptions,
+        priority: Priority,
+        route: &RouteContext,
+    ) -> Result<PlaylistEnqueued, String> {
+        let persistence = app.try_state::<crate::persistence::PersistenceManager>();
+        let (selected, mut skipped) = playlist::select(&request.entries, &request.selection, |id| {
+            persistence.as_ref().is_some_and(|p| p.archive().contains(id))
+        })?;
+        if selected.is_empty() {
+            return Err("No playlist entries match the selection".to_string());
+        }
+
+        let group_id = uuid::Uuid::new_v4().to_string();
+        let route = RouteContext { playlist: route.playlist.clone().or(request.playlist_id.clone()), ..route.clone() };
+        let (mut children, mut tasks) = (Vec::new(), Vec::new());
+        for (index, entry) in selected {
+            let mut child = options.clone();
+            child.playlist = Some(PlaylistRef {
+                group_id: group_id.clone(),
+                title: request.title.clone(),
+                url: request.url.clone(),
+                index,
+            });
+            child.archive_id = entry.archive_id.clone();
+            self.apply_routing(app, &entry.url, &mut child, &route);
+
+            let id = uuid::Uuid::new_v4().to_string();
+            match self.build_task(app, entry.url.clone(), id.clone(), entry.title.clone(), child, priority) {
+                Ok(task) => {
+                    tasks.push(task);
+                    children.push(PlaylistChild { task_id: id, entry_id: entry.id.clone(), index });
+                }
+                // Refused by the download archive
+                Err(e) => {
+                    log::info!("[PLAYLIST] Not enqueueing entry {}: {}", index, e);
+                    skipped += 1;
+                }
+            }
+        }
+        if children.is_empty() {
+            return Err("Every selected entry has already been downloaded".to_string());
+        }
+
+        log::info!("[PLAYLIST] Enqueued {} entries of {} as group {} ({} skipped)", children.len(), request.url, group_id, skipped);
+        let task_ids: Vec<String> = children.iter().map(|c| c.task_id.clone()).collect();
+        self.playlists.lock().unwrap().insert(group_id.clone(), PlaylistGroup {
+            id: group_id.clone(),
+            title: request.title,
+            url: request.url,
+            task_ids: task_ids.clone(),
+        });
+        self.tasks
+            .lock()
+            .unwrap()
+            .extend(tasks.into_iter().map(|t| (t.id.clone(), Arc::new(Mutex::new(t)))));
+        // Placed and saved as one batch, however long the playlist
+        self.queue_new(app, &task_ids);
+        self.process_queue(app.clone());
+        Ok(PlaylistEnqueued { group_id, children, skipped })
+    }
+
+    /// Rebuilds playlist groups from the child tasks loaded at startup.
+    pub fn restore_playlists(&self) {
+        let mut children: Vec<(PlaylistRef, String)> = self
+            .tasks
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|t| {
+                let task = t.lock().unwrap();
+                task.options.playlist.clone().map(|p| (p, task.id.clone()))
+            })
+            .collect();
+        children.sort_by_key(|(p, _)| p.index);
+
+        let mut playlists = self.playlists.lock().unwrap();
+        for (playlist, task_id) in children {
+            playlists
+                .entry(playlist.group_id.clone())
+                .or_insert_with(|| PlaylistGroup {
+                    id: playlist.group_id,
+                    title: playlist.title,
+                    url: playlist.url,
+                    task_ids: Vec::new(),
+                })
+                .task_ids
+                .push(task_id);
+        }
+    }
+
+    /// The aggregate of every playlist group that still has child tasks.
+    pub fn playlist_progress(&self) -> Vec<PlaylistProgressPayload> {
+        let groups: Vec<PlaylistGroup> = self.playlists.lock().unwrap().values().cloned().collect();
+        let tasks = self.tasks.lock().unwrap();
+        groups
+            .iter()
+            .filter_map(|group| {
+                let children: Vec<_> = group
+                    .task_ids
+                    .iter()
+                    .filter_map(|id| tasks.get(id))
+                    .map(|t| {
+                        let task = t.lock().unwrap();
+                        (task.id.clone(), task.status.clone(), task.progress)
+                    })
+                    .collect();
+                (!children.is_empty()).then(|| playlist::aggregate(group, &children))
+            })
+            .collect()
+    }
+
+    /// Emits `playlist-progress` for groups whose aggregate changed, and
+    /// `playlist-removed` once all of a group's children have been removed.
+    pub fn spawn_playlist_monitor<R: Runtime>(app: AppHandle<R>) {
+        tauri::async_runtime::spawn(async move {
+            let interval = std::time::Duration::from_millis(SYSTEM_GUARDRAILS.playlist_progress_ms);
+            loop {
+                tokio::time::sleep(interval).await;
+                let manager = app.state::<DownloadManager>();
+                let current = manager.playlist_progress();
+
+                let mut emitted = manager.playlist_emitted.lock().unwrap();
+                for payload in &current {
+                    if emitted.get(&payload.id) != Some(payload) {
+                        let _ = app.emit("playlist-progress", payload);
+                        emitted.insert(payload.id.clone(), payload.clone());
+                    }
+                }
+                // Checked again under the lock, a group may have been added since
+                let mut playlists = manager.playlists.lock().unwrap();
+                let tasks = manager.tasks.lock().unwrap();
+                playlists.retain(|id, group| {
+                    let alive = group.task_ids.iter().any(|t| tasks.contains_key(t));
+                    if !alive {
+                        log::info!("[PLAYLIST] Group {} has no entries left", id);
+                        emitted.remove(id);
+                        let _ = app.emit("playlist-removed", id);
+                    }
+                    alive
+                });
+            }
+        });
+    }
+
+    /// Writes one task's current record to the store. A no-op until persistence has been set up.
+    pub fn persist_task<R: Runtime>(&self, app: &AppHandle<R>, id: &str) {
+        let Some(persistence) = app.try_state::<crate::persistence::PersistenceManager>() else {
+            return;
+        };
+        let task_ref = self.tasks.lock().unwrap().get(id).cloned();
+        if let Some(task_ref) = task_ref {
+            if let Err(e) = persistence.save_task(&task_ref.lock().unwrap()) {
+                log::error!("[PERSISTENCE] Failed to save task {}: {}", id, e);
+            }
+        }
+    }
+
+    pub fn persist_tasks<R: Runtime>(&self, app: &AppHandle<R>, ids: &[String]) {
+        let Some(persistence) = app.try_state::<crate::persistence::PersistenceManager>() else {
+            return;
+        };
+        let task_refs: Vec<_> = {
+            let tasks = self.tasks.lock().unwrap();
+            ids.iter().filter_map(|id| tasks.get(id).cloned()).collect()
+        };
+        let records: Vec<_> = task_refs
+            .iter()
+            .map(|t| crate::persistence::PersistedTask::from_task(&t.lock().unwrap()))
+            .collect();
+        if let Err(e) = persistence.save_tasks(&records) {
+            log::error!("[PERSISTENCE] Failed to save {} tasks: {}", ids.len(), e);
+        }
+    }
+
+    /// Queued and paused tasks in the order the scheduler will consider them.
+    pub fn pending_queue(&self) -> Vec<Arc<Mutex<DownloadTask>>> {
+        let mut pending: Vec<_> = {
+            let tasks = self.tasks.lock().unwrap();
+            tasks
+                .values()
+                .filter(|t| matches!(t.lock().unwrap().status, DownloadStatus::Queued | DownloadStatus::Paused))
+                .cloned()
+                .collect()
+        };
+        pending.sort_by_cached_key(|t| t.lock().unwrap().queue_key());
+        pending
+    }
+
+    pub fn queue_order(&self) -> Vec<String> {
+        self.pending_queue().iter().map(|t| t.lock().unwrap().id.clone()).collect()
+    }
+
+    /// Gives `queue` consecutive positions and returns the ids whose position changed.
+    fn renumber(queue: &[Arc<Mutex<DownloadTask>>]) -> Vec<String> {
+        let mut changed = Vec::new();
+        for (position, task_ref) in queue.iter().enumerate() {
+            let mut task = task_ref.lock().unwrap();
+            if task.queue_position != position as i64 {
+                task.queue_position = position as i64;
+                changed.push(task.id.clone());
+            }
+        }
+        changed
+    }
+
+    /// Places a task that just became pending behind every waiting task of
+    /// equal or higher priority.
+    fn enqueue(&self, id: &str) -> Vec<String> {
+        self.enqueue_all(&[id.to_string()])
+    }
+
+    /// `enqueue` for several tasks of one priority at once, e.g. the entries
+    /// of a playlist. They stay in the order of `ids`.
+    fn enqueue_all(&self, ids: &[String]) -> Vec<String> {
+        let order: HashMap<&str, usize> = ids.iter().enumerate().map(|(i, id)| (id.as_str(), i)).collect();
+        let (mut added, mut queue): (Vec<_>, Vec<_>) = self
+            .pending_queue()
+            .into_iter()
+            .partition(|t| order.contains_key(t.lock().unwrap().id.as_str()));
+        let Some(priority) = added.first().map(|t| t.lock().unwrap().priority) else {
+            return Vec::new();
+        };
+        added.sort_by_cached_key(|t| order[t.lock().unwrap().id.as_str()]);
+        let index = queue
+            .iter()
+            .rposition(|t| t.lock().unwrap().priority >= priority)
+            .map_or(0, |i| i + 1);
+        queue.splice(index..index, added);
+        Self::renumber(&queue)
     }
 
-    pub fn process_queue<R: Runtime>(&self, app: AppHandle<R>, path: Option<String>, format_spec: Option<String>, cookies: Option<String>) {
+    /// Moves a waiting task to `index` in the queue (clamped to the end).
+    /// Returns the ids whose stored position changed.
+    pub fn reorder_download(&self, id: &str, index: usize) -> Result<Vec<String>, String> {
+        let mut queue = self.pending_queue();
+        let from = queue
+            .iter()
+            .position(|t| t.lock().unwrap().id == id)
+            .ok_or_else(|| "Only queued or paused tasks can be reordered".to_string())?;
+        let task_ref = queue.remove(from);
+        let index = index.min(queue.len());
+        queue.insert(index, task_ref);
+        log::info!("[QUEUE] Moved {} from position {} to {}", id, from, index);
+        Ok(Self::renumber(&queue))
+    }
+
+    /// Changes a task's priority. A waiting task is re-slotted as if it had just been enqueued.
+    pub fn set_priority(&self, id: &str, priority: Priority) -> Result<Vec<String>, String> {
+        let task_ref = self
+            .tasks
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| "Task not found".to_string())?;
+        let pending = {
+            let mut task = task_ref.lock().unwrap();
+            task.priority = priority;
+            matches!(task.status, DownloadStatus::Queued | DownloadStatus::Paused)
+        };
+        let mut changed = if pending { self.enqueue(id) } else { Vec::new() };
+        if !changed.iter().any(|c| c == id) {
+            changed.push(id.to_string());
+        }
+        Ok(changed)
+    }
+
+    pub fn process_queue<R: Runtime>(&self, app: AppHandle<R>) {
         let tasks_arc = self.tasks.clone();
-        let max_concurrent = self.max_concurrent;
+        let max_concurrent = self.max_concurrent.load(Ordering::SeqCst);
+
+        let host_limits = self.host_limits.lock().unwrap().clone();
+        let bucket_of = |task: &DownloadTask| url_host(&task.url).map(|h| host_limits.bucket(&h));
 
-        let active_count = {
+        let mut active_count = 0;
+        let mut active_per_bucket: HashMap<String, usize> = HashMap::new();
+        // Bytes running downloads are still expected to write, per folder
+        let mut reserved: HashMap<std::path::PathBuf, u64> = HashMap::new();
+        {
             let tasks = tasks_arc.lock().unwrap();
-            tasks.values().filter(|t| {
+            for t in tasks.values() {
                 let task = t.lock().unwrap();
-                matches!(task.status, DownloadStatus::Preparing | DownloadStatus::Downloading | DownloadStatus::Merging)
-            }).count()
-        };
+                if matches!(task.status, DownloadStatus::Preparing | DownloadStatus::Downloading | DownloadStatus::Merging) {
+                    active_count += 1;
+                    if let Some(bucket) = bucket_of(&task) {
+                        *active_per_bucket.entry(bucket).or_default() += 1;
+                    }
+                    if let Some(size) = task.options.estimated_size {
+                        let remaining = size.saturating_sub(task.downloaded_bytes.unwrap_or(0));
+                        *reserved.entry(output_dir(&task.options)).or_default() += remaining;
+                    }
+                }
+            }
+        }
 
         if active_count >= max_concurrent {
             return;
         }
 
+        let in_window = self.schedule.lock().unwrap().allows(chrono::Local::now().naive_local());
+        let safety_margin = self.disk_space.lock().unwrap().safety_margin;
+        let mut free_space: HashMap<std::path::PathBuf, Option<u64>> = HashMap::new();
+        let mut too_big: Vec<DiskSpacePayload> = Vec::new();
+
+        // A saturated host only holds back its own tasks; the rest of the queue keeps moving
+        let now = Instant::now();
         let next_task_id = {
-            let tasks = tasks_arc.lock().unwrap();
-            tasks.iter().find(|(_, t)| {
+            self.pending_queue().iter().find(|t| {
                 let task = t.lock().unwrap();
-                task.status == DownloadStatus::Queued
-            }).map(|(id, _)| id.clone())
+                if task.status != DownloadStatus::Queued || task.next_attempt_at.is_some_and(|at| at > now) {
+                    return false;
+                }
+                if task.held_by_schedule(in_window) {
+                    return false;
+                }
+                let host_free = match bucket_of(&task) {
+                    Some(bucket) => host_limits
+                        .limit_for(&bucket)
+                        .is_none_or(|limit| active_per_bucket.get(&bucket).copied().unwrap_or(0) < limit),
+                    None => true,
+                };
+                if !host_free {
+                    return false;
+                }
+                // Tasks that would not fit wait; unknown free space never blocks
+                let dir = output_dir(&task.options);
+                let Some(available) = *free_space.entry(dir.clone()).or_insert_with(|| available_space(&dir)) else {
+                    return true;
+                };
+                let required = task.options.estimated_size.unwrap_or(0)
+                    + reserved.get(&dir).copied().unwrap_or(0)
+                    + safety_margin;
+                if available < required {
+                    too_big.push(DiskSpacePayload {
+                        path: dir.to_string_lossy().to_string(),
+                        available,
+                        required,
+                        task_ids: vec![task.id.clone()],
+                        paused: false,
+                    });
+                    return false;
+                }
+                true
+            }).map(|t| t.lock().unwrap().id.clone())
         };
 
+        {
+            let mut held = self.space_held.lock().unwrap();
+            for payload in too_big {
+                if held.insert(payload.task_ids[0].clone()) {
+                    log::warn!("[QUEUE] Holding {}: needs {} bytes in {}, {} free", payload.task_ids[0], payload.required, payload.path, payload.available);
+                    let _ = app.emit("disk-space-low", payload);
+                }
+            }
+            if let Some(id) = &next_task_id {
+                held.remove(id);
+            }
+        }
+
         if let Some(id) = next_task_id {
             let task_ref = {
                 let tasks = tasks_arc.lock().unwrap();
                 tasks.get(&id).unwrap().clone()
             };
 
-            {
-                let mut task = task_ref.lock().unwrap();
-                if !task.transition(DownloadStatus::Preparing) {
-                    return;
-                }
+            if !task_ref.lock().unwrap().begin_attempt() {
+                return;
             }
 
             // Start the actual download in a spawn
             let app_inner = app.clone();
-            let url_inner = {
+            // Each task carries the options it was enqueued with
+            let (url_inner, options, retry_count) = {
                 let task = task_ref.lock().unwrap();
-                task.url.clone()
+                (task.url.clone(), task.options.clone(), task.retry_count)
             };
-            let path_inner = path.clone();
-            let format_spec_inner = format_spec.clone();
-            let cookies_inner = cookies.clone();
+            // This task already counts as active, having just moved to Preparing
+            let rate_limit = self.rate_share(active_count + 1, &options);
+            task_ref.lock().unwrap().rate_limit = rate_limit;
 
             tauri::async_runtime::spawn(async move {
                 let fragments = SYSTEM_GUARDRAILS.default_fragments.to_string();
@@ -570,11 +2023,13 @@ impl DownloadManager {
                     "--progress-template",
                     "%(progress.downloaded_bytes)s|%(progress.total_bytes)s|%(progress.speed)s|%(progress.eta)s",
                     "--no-warnings",
+                    // Pick up an existing .part file left behind by a failed attempt
+                    "--continue",
                 ];
 
                 // Cookie Handling
                 let mut cookie_file_path = None;
-                if let Some(ref cookie_data) = cookies_inner {
+                if let Some(ref cookie_data) = options.cookies {
                     let temp_dir = std::env::temp_dir();
                     let file_path = temp_dir.join(format!("vidflow_cookies_{}.txt", id));
                     if let Ok(mut file) = fs::File::create(&file_path) {
@@ -595,14 +2050,14 @@ impl DownloadManager {
                 args.push("--embed-thumbnail");
 
                 let path_string;
-                if let Some(p) = &path_inner {
+                if let Some(p) = &options.output_dir {
                     path_string = p.clone();
                     args.push("-P");
                     args.push(&path_string);
                 }
 
                 let format_arg;
-                if let Some(ref spec) = format_spec_inner {
+                if let Some(ref spec) = options.format_spec {
                     if spec == "audio" {
                         args.push("-x");
                         args.push("--audio-format");
@@ -617,24 +2072,123 @@ impl DownloadManager {
                         args.push(&ffmpeg_path);
                     }
                 }
+
+                let (template_arg, trim_arg);
+                if let Some(template) = options.output_template.as_deref().and_then(|t| OutputTemplate::parse(t).ok()) {
+                    template_arg = template.to_ytdlp();
+                    trim_arg = SYSTEM_GUARDRAILS.max_filename_len.to_string();
+                    args.push("-o");
+                    args.push(&template_arg);
+                    args.push("--windows-filenames");
+                    args.push("--trim-filenames");
+                    args.push(&trim_arg);
+                }
+
+                let rate_arg;
+                if let Some(rate) = rate_limit {
+                    rate_arg = rate.to_string();
+                    args.push("--limit-rate");
+                    args.push(&rate_arg);
+                }
+
+                for extra in &options.extra_args {
+                    args.push(extra);
+                }
                 
+                // Existing files: Skip is yt-dlp's own behaviour, Overwrite is a flag,
+                // Rename and Version need to know the file name up front
+                let policy = options.conflict_policy.unwrap_or_default();
+                if policy == ConflictPolicy::Overwrite {
+                    args.push("--force-overwrites");
+                }
+
                 args.push(&url_inner);
 
-                match app_inner.shell()
-                    .command(yt_dlp_path)
-                    .args(args)
-                    .spawn() {
+                let mut conflict_error = None;
+                // A relaunch keeps the name an earlier run was renamed to, so it
+                // continues that run's partial file instead of picking the next free name
+                let mut conflict_template = options.conflict_output.clone();
+                if conflict_template.is_none() && matches!(policy, ConflictPolicy::Rename | ConflictPolicy::Version) {
+                    // The printed name is the one before post-processing; -x converts it afterwards
+                    let audio = options.format_spec.as_deref() == Some("audio");
+                    let target = predict_filename(&yt_dlp_path, &args)
+                        .await
+                        .map(|p| if audio { p.with_extension("mp3") } else { p })
+                        .map(|p| output_dir(&options).join(p));
+                    if let Some(target) = target.filter(|p| p.exists()) {
+                        match conflict::resolve(policy, &target) {
+                            Ok((template, outcome)) => {
+                                log::info!("[DOWNLOAD] {} already exists, resolved as {:?}", target.display(), outcome);
+                                {
+                                    let mut task = task_ref.lock().unwrap();
+                                    task.conflict = outcome;
+                                    task.options.conflict_output = template.clone();
+                                }
+                                if template.is_some() {
+                                    app_inner.state::<DownloadManager>().persist_task(&app_inner, &id);
+                                }
+                                conflict_template = template;
+                            }
+                            Err(e) => conflict_error = Some(DownloadError::Unknown(e)),
+                        }
+                    }
+                }
+                if let Some(template) = &conflict_template {
+                    args.insert(args.len() - 1, "-o");
+                    args.insert(args.len() - 1, template);
+                }
+
+                let spawned = match conflict_error {
+                    Some(error) => Err(error),
+                    None => crate::process::spawn_in_group(&yt_dlp_path, &args).map_err(|e| DownloadError::Spawn(e.to_string())),
+                };
+                match spawned {
                     Ok((mut rx, child)) => {
                         {
                             let mut task = task_ref.lock().unwrap();
-                            task.child = Some(child);
-                            let _ = task.transition(DownloadStatus::Downloading);
+                            if task.status == DownloadStatus::Cancelled {
+                                // Cancelled while preparing; stop the process we just started
+                                task.stop_reason = Some(StopReason::Cancel);
+                                child.terminate();
+                            } else {
+                                task.child = Some(child);
+                                let _ = task.transition(DownloadStatus::Downloading);
+                            }
                         }
+                        // Running tasks give up part of their share to this one
+                        app_inner.state::<DownloadManager>().rebalance_bandwidth();
+
+                        // Keep the tail of stderr so a failure can be classified
+                        let mut stderr_tail: VecDeque<String> = VecDeque::new();
 
                         while let Some(event) = rx.recv().await {
                              match event {
+                                CommandEvent::Stderr(line) => {
+                                    let line_str = String::from_utf8_lossy(&line).trim().to_string();
+                                    if !line_str.is_empty() {
+                                        log::warn!("[DOWNLOAD] yt-dlp stderr for {}: {}", id, line_str);
+                                        if stderr_tail.len() >= SYSTEM_GUARDRAILS.stderr_tail_lines {
+                                            stderr_tail.pop_front();
+                                        }
+                                        stderr_tail.push_back(line_str);
+                                    }
+                                }
                                 CommandEvent::Stdout(line) => {
                                     let line_str = String::from_utf8_lossy(&line);
+
+                                    if let Some(path) = cleanup::announced_path(&line_str) {
+                                        let mut task = task_ref.lock().unwrap();
+                                        if !task.artifacts.contains(&path) {
+                                            task.artifacts.push(path);
+                                        }
+                                    }
+                                    // e.g. "[youtube] Extracting URL: https://..."
+                                    if let Some(name) = line_str.strip_prefix('[').and_then(|l| l.split_once("] Extracting URL:")).map(|(n, _)| n) {
+                                        let mut task = task_ref.lock().unwrap();
+                                        if task.extractor.is_none() {
+                                            task.extractor = Some(name.to_string());
+                                        }
+                                    }
                                     
                                      if line_str.contains("[download] Destination:") {
                                          let path_part = line_str.split("Destination:").nth(1).unwrap_or("").trim();
@@ -661,16 +2215,22 @@ impl DownloadManager {
                                              log::info!("[DOWNLOAD] Captured existing file path: {}", path_part);
                                              let mut task = task_ref.lock().unwrap();
                                              task.final_path = Some(std::path::PathBuf::from(path_part));
+                                             task.conflict = Some(ConflictOutcome::Skipped { path: path_part.to_string() });
                                          }
                                      }
 
+                                    // Printed for --force-overwrites
+                                    if let Some(path_part) = line_str.split("Deleting existing file").nth(1).map(str::trim) {
+                                        if !path_part.is_empty() {
+                                            task_ref.lock().unwrap().conflict = Some(ConflictOutcome::Overwritten { path: path_part.to_string() });
+                                        }
+                                    }
+
                                     if line_str.contains("[Merger]") {
-                                        let mut task = task_ref.lock().unwrap();
-                                        let _ = task.transition(DownloadStatus::Merging);
-                                        
-                                        let final_path = {
-                                            let task = task_ref.lock().unwrap();
-                                            task.final_path.as_ref().map(|p| p.to_string_lossy().to_string())
+                                        let (final_path, conflict) = {
+                                            let mut task = task_ref.lock().unwrap();
+                                            let _ = task.transition(DownloadStatus::Merging);
+                                            (task.final_path.as_ref().map(|p| p.to_string_lossy().to_string()), task.conflict.clone())
                                         };
                                         
                                         let payload = DownloadProgressPayload {
@@ -683,7 +2243,10 @@ impl DownloadManager {
                                             downloaded_bytes: None,
                                             can_retry: Some(false),
                                             error_message: None,
+                                            error_code: None,
                                             final_path,
+                                            retry_count,
+                                            conflict,
                                             version: SYSTEM_GUARDRAILS.ipc_version,
                                         };
                                         let _ = app_inner.emit("download-progress", payload);
@@ -713,9 +2276,9 @@ impl DownloadManager {
                                             task.downloaded_bytes = Some(downloaded);
                                         }
 
-                                        let final_path = {
+                                        let (final_path, conflict) = {
                                             let task = task_ref.lock().unwrap();
-                                            task.final_path.as_ref().map(|p| p.to_string_lossy().to_string())
+                                            (task.final_path.as_ref().map(|p| p.to_string_lossy().to_string()), task.conflict.clone())
                                         };
 
                                         let payload = DownloadProgressPayload {
@@ -728,22 +2291,41 @@ impl DownloadManager {
                                             downloaded_bytes: Some(downloaded),
                                             can_retry: Some(false),
                                             error_message: None,
+                                            error_code: None,
                                             final_path,
+                                            retry_count,
+                                            conflict,
                                             version: SYSTEM_GUARDRAILS.ipc_version,
                                         };
                                         let _ = app_inner.emit("download-progress", payload);
                                     }
                                 }
                                 CommandEvent::Terminated(payload) => {
-                                      let (current_status, final_path) = {
+                                      let mut retry_delay = None;
+                                      let (current_status, final_path, stop_reason) = {
                                          let mut task = task_ref.lock().unwrap();
                                          let s = task.status.clone();
                                          let p = task.final_path.clone();
                                          task.child = None;
-                                         (s, p)
+                                         (s, p, task.stop_reason.take())
                                       };
                                       
-                                       let status = if payload.code == Some(0) {
+                                       let status = if stop_reason == Some(StopReason::Cancel) || current_status == DownloadStatus::Cancelled {
+                                           // The whole process group is gone; only now is the cancel complete
+                                           let mut task = task_ref.lock().unwrap();
+                                           if task.transition(DownloadStatus::Cancelled) {
+                                               task.error = Some(DownloadError::Cancelled);
+                                           }
+                                           log::info!("[DOWNLOAD] {} cancelled, process group exited", id);
+                                           DownloadStatus::Cancelled
+                                       } else if stop_reason == Some(StopReason::Pause) && current_status == DownloadStatus::Paused && payload.code != Some(0) {
+                                          log::info!("[DOWNLOAD] {} paused; partial files kept for resume", id);
+                                          DownloadStatus::Paused
+                                       } else if payload.code == Some(0) {
+                                           // Also ends a pause whose process finished before it could be stopped
+                                           if stop_reason == Some(StopReason::Pause) {
+                                               log::info!("[DOWNLOAD] {} finished while pausing", id);
+                                           }
                                            if let Some(ref path) = final_path {
                                                // Check if file exists first
                                                if !path.exists() {
@@ -772,37 +2354,88 @@ impl DownloadManager {
                                                let _ = task.transition(DownloadStatus::Completed);
                                                DownloadStatus::Completed
                                            }
-                                       } else if current_status == DownloadStatus::Cancelled {
-                                          DownloadStatus::Cancelled
+                                      } else if stop_reason == Some(StopReason::Restart) && task_ref.lock().unwrap().requeue_stopped() {
+                                          let manager = app_inner.state::<DownloadManager>();
+                                          if let Ok(changed) = manager.reorder_download(&id, 0) {
+                                              manager.persist_tasks(&app_inner, &changed);
+                                          }
+                                          DownloadStatus::Queued
                                       } else {
+                                          let stderr_text = Vec::from(stderr_tail.clone()).join("\n");
+                                          let error = DownloadError::from_stderr(&stderr_text);
+                                          let policy = app_inner.state::<DownloadManager>().retry_policy.lock().unwrap().clone();
+
                                           let mut task = task_ref.lock().unwrap();
                                           let _ = task.transition(DownloadStatus::Error);
-                                          DownloadStatus::Error
+                                          let next = match policy.next_delay(error.class(), task.retry_count) {
+                                              Some(delay) if task.transition(DownloadStatus::Queued) => {
+                                                  log::info!("[DOWNLOAD] {} failed ({}), retrying in {:?}", id, error, delay);
+                                                  task.retry_count += 1;
+                                                  task.next_attempt_at = Some(Instant::now() + delay);
+                                                  retry_delay = Some(delay);
+                                                  DownloadStatus::Queued
+                                              }
+                                              _ => {
+                                                  log::warn!("[DOWNLOAD] {} failed ({}), not retrying", id, error);
+                                                  DownloadStatus::Error
+                                              }
+                                          };
+                                          task.error = Some(error);
+                                          next
                                       };
 
-                                     // Explicit cleanup
-                                     if status == DownloadStatus::Cancelled || status == DownloadStatus::Error {
-                                         let dest = {
-                                             let task = task_ref.lock().unwrap();
-                                             task.final_path.clone()
+                                     if status == DownloadStatus::Completed {
+                                         let archive_id = {
+                                             let mut task = task_ref.lock().unwrap();
+                                             task.file_size = final_path.as_ref().and_then(|p| fs::metadata(p).ok()).map(|m| m.len());
+                                             task.options.archive_id.clone()
                                          };
-                                         if let Some(dest) = dest {
-                                             let _ = fs::remove_file(format!("{}.part", dest.display()));
-                                             let _ = fs::remove_file(format!("{}.ytdl", dest.display()));
+                                         if let (Some(archive_id), Some(persistence)) = (archive_id, app_inner.try_state::<crate::persistence::PersistenceManager>()) {
+                                             if let Err(e) = persistence.archive().add(&archive_id) {
+                                                 log::error!("[ARCHIVE] Failed to record {}: {}", archive_id, e);
+                                             }
+                                         }
+                                     }
+
+                                     // Explicit cleanup. Failures keep their fragments for as long as a retry could continue them.
+                                     {
+                                         let policy = app_inner.state::<DownloadManager>().retry_policy.lock().unwrap().clone();
+                                         let mut task = task_ref.lock().unwrap();
+                                         let resumable = cleanup::is_resumable(&status, task.error.as_ref(), &policy);
+                                         task.remove_artifacts(cleanup::cleanup_after(&status, resumable));
+                                         if matches!(status, DownloadStatus::Error | DownloadStatus::Cancelled) {
+                                             task.restore_versioned();
                                          }
                                      }
                                      
+                                      let (error, retry_count, progress, conflict) = {
+                                          let task = task_ref.lock().unwrap();
+                                          (task.error.clone(), task.retry_count, task.progress, task.conflict.clone())
+                                      };
                                       let final_payload = DownloadProgressPayload {
                                          id: id.clone(),
-                                         progress: if status == DownloadStatus::Completed { 100.0 } else { 0.0 },
+                                         progress: match status {
+                                             DownloadStatus::Completed => 100.0,
+                                             DownloadStatus::Cancelled => 0.0,
+                                             // Restarts and pauses continue from the partial file
+                                             _ if stop_reason.is_some() => progress,
+                                             _ => 0.0,
+                                         },
                                          speed: None,
                                          eta: None,
                                          status: status.clone(),
                                          total_size: None,
                                          downloaded_bytes: None,
                                          can_retry: Some(status == DownloadStatus::Error),
-                                         error_message: if status == DownloadStatus::Error { Some("Download failed".to_string()) } else { None },
+                                         error_message: match (&error, retry_delay) {
+                                             (Some(e), Some(delay)) => Some(format!("{}; retrying in {}s", e, delay.as_secs().max(1))),
+                                             (Some(e), None) => Some(e.to_string()),
+                                             _ => None,
+                                         },
+                                         error_code: error.as_ref().map(|e| e.code().to_string()),
                                          final_path: final_path.as_ref().map(|p| p.to_string_lossy().to_string()),
+                                         retry_count,
+                                         conflict,
                                          version: SYSTEM_GUARDRAILS.ipc_version,
                                       };
                                       log::info!("[DOWNLOAD] Emitting final status for {}: {:?}, final_path: {:?}", id, status, final_path);
@@ -815,13 +2448,28 @@ impl DownloadManager {
 
                                      // Process next in queue
                                      let manager = app_inner.state::<DownloadManager>();
-                                     
-                                     // Trigger persistence save
-                                     if let Some(persistence) = app_inner.try_state::<crate::persistence::PersistenceManager>() {
-                                         let _ = persistence.save_tasks(&manager.tasks.lock().unwrap());
+                                     manager.release_space(&id);
+                                     // An automatic retry queues up like a manual one, behind its priority peers
+                                     let mut changed = if retry_delay.is_some() { manager.enqueue(&id) } else { Vec::new() };
+                                     if !changed.iter().any(|c| c == &id) {
+                                         changed.push(id.clone());
+                                     }
+                                     manager.persist_tasks(&app_inner, &changed);
+
+                                     manager.process_queue(app_inner.clone());
+                                     // A freed slot leaves more bandwidth for the rest
+                                     if stop_reason != Some(StopReason::Restart) {
+                                         manager.rebalance_bandwidth();
                                      }
 
-                                     manager.process_queue(app_inner.clone(), path.clone(), format_spec.clone(), cookies.clone());
+                                     // Wake the queue once the backoff for this task has elapsed
+                                     if let Some(delay) = retry_delay {
+                                         let app_retry = app_inner.clone();
+                                         tauri::async_runtime::spawn(async move {
+                                             tokio::time::sleep(delay).await;
+                                             app_retry.state::<DownloadManager>().process_queue(app_retry.clone());
+                                         });
+                                     }
                                      
                                      return;
                                 }
@@ -829,23 +2477,17 @@ impl DownloadManager {
                             }
                         }
                     }
-                    Err(_) => {
-                        {
+                    Err(error) => {
+                        let payload = {
                             let mut task = task_ref.lock().unwrap();
-                            let _ = task.transition(DownloadStatus::Error);
-                        }
-                        let payload = DownloadProgressPayload {
-                            id: id.clone(),
-                            progress: 0.0,
-                            speed: None,
-                            eta: None,
-                            status: DownloadStatus::Error,
-                            total_size: None,
-                            downloaded_bytes: None,
-                            can_retry: Some(true),
-                            error_message: Some("Failed to start process".to_string()),
-                            final_path: None,
-                            version: SYSTEM_GUARDRAILS.ipc_version,
+                            if task.status == DownloadStatus::Cancelled {
+                                // Cancelled while preparing; the cancel stands
+                                log::info!("[DOWNLOAD] {} cancelled before it started ({})", id, error);
+                            } else if task.transition(DownloadStatus::Error) {
+                                task.error = Some(error);
+                            }
+                            task.restore_versioned();
+                            task.to_payload()
                         };
                         let _ = app_inner.emit("download-progress", payload);
                         
@@ -854,17 +2496,101 @@ impl DownloadManager {
                         }
 
                         let manager = app_inner.state::<DownloadManager>();
-                        
-                        // Trigger persistence save
-                        if let Some(persistence) = app_inner.try_state::<crate::persistence::PersistenceManager>() {
-                            let _ = persistence.save_tasks(&manager.tasks.lock().unwrap());
-                        }
+                        manager.release_space(&id);
+                        manager.persist_task(&app_inner, &id);
 
-                        manager.process_queue(app_inner.clone(), path, format_spec, cookies);
+                        manager.process_queue(app_inner.clone());
                     }
                 }
             });
+
+            // Keep filling free slots; the task above now counts as active
+            self.process_queue(app);
         }
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task() -> DownloadTask {
+        DownloadTask::new("t".into(), "https://example.com/v".into(), "Video".into(), DownloadOptions::default())
+    }
+
+    fn running_task() -> DownloadTask {
+        let mut task = task();
+        assert!(task.begin_attempt());
+        assert!(task.transition(DownloadStatus::Downloading));
+        task
+    }
+
+    #[test]
+    fn queued_task_waits_for_a_window() {
+        let task = task();
+        assert!(task.held_by_schedule(false));
+        assert!(!task.held_by_schedule(true));
+        let start_now = DownloadTask { options: DownloadOptions { ignore_schedule: true, ..Default::default() }, ..task };
+        assert!(!start_now.held_by_schedule(false));
+    }
+
+    #[test]
+    fn window_closing_while_a_download_runs_does_not_park_it() {
+        // The timer rebalances at the boundary; the restarted process exits and is re-queued
+        let mut task = running_task();
+        assert!(task.requeue_stopped());
+        assert_eq!(task.status, DownloadStatus::Queued);
+        assert!(!task.held_by_schedule(false));
+
+        // The bypass covers that one relaunch, not later retries
+        assert!(task.begin_attempt());
+        assert!(task.transition(DownloadStatus::Downloading));
+        assert!(task.transition(DownloadStatus::Error));
+        assert!(task.transition(DownloadStatus::Queued));
+        assert!(task.held_by_schedule(false));
+    }
+
+    #[test]
+    fn resumed_kill_and_continue_pause_starts_outside_a_window() {
+        let mut task = running_task();
+        assert!(task.transition(DownloadStatus::Paused));
+        assert_eq!(DownloadManager::resume_task(&mut task), Some(DownloadStatus::Queued));
+        assert!(!task.held_by_schedule(false));
+    }
+
+    #[test]
+    fn cancelling_a_held_task_releases_its_space_hold() {
+        let manager = DownloadManager::new();
+        manager.tasks.lock().unwrap().insert("t".into(), Arc::new(Mutex::new(task())));
+        manager.space_held.lock().unwrap().insert("t".into());
+        assert!(manager.cancel_download("t"));
+        assert!(manager.space_held.lock().unwrap().is_empty());
+        // A task that is already cancelled cannot be cancelled again
+        assert!(!manager.cancel_download("t"));
+        assert!(!manager.cancel_download("missing"));
+    }
+
+    #[test]
+    fn playlist_entries_queue_together_in_order_behind_their_priority() {
+        let manager = DownloadManager::new();
+        let add = |id: &str, priority: Priority| {
+            let mut task = DownloadTask::new(id.into(), "https://example.com/v".into(), id.into(), DownloadOptions::default());
+            task.priority = priority;
+            manager.tasks.lock().unwrap().insert(id.into(), Arc::new(Mutex::new(task)));
+        };
+        add("high", Priority::High);
+        add("low", Priority::Low);
+        manager.enqueue("high");
+        manager.enqueue("low");
+        // Inserted in reverse, queued in the order they are listed
+        let ids: Vec<String> = ["e0", "e1", "e2"].iter().map(|id| id.to_string()).collect();
+        for id in ids.iter().rev() {
+            add(id, Priority::Normal);
+        }
+        let changed = manager.enqueue_all(&ids);
+        assert_eq!(manager.queue_order(), ["high", "e0", "e1", "e2", "low"]);
+        assert!(changed.contains(&"low".to_string()));
+        assert!(!changed.contains(&"high".to_string()));
+    }
+}
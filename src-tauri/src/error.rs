@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use crate::retry::FailureClass;
+
+/// Why a download did not finish. Serialized with a stable `code` so the
+/// frontend and the persisted history can match on it.
+#[derive(Error, Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "code", content = "detail", rename_all = "snake_case")]
+pub enum DownloadError {
+    #[error("Network error: {0}")]
+    Network(String),
+    #[error("Server responded with HTTP {0}")]
+    Http(u16),
+    #[error("This video is not available in your country")]
+    GeoBlocked,
+    #[error("This video requires signing in; add cookies and try again")]
+    LoginRequired,
+    #[error("This video is private or has been removed")]
+    Unavailable,
+    #[error("This URL is not supported")]
+    UnsupportedUrl,
+    #[error("Post-processing with ffmpeg failed: {0}")]
+    Ffmpeg(String),
+    #[error("Not enough disk space to finish the download")]
+    DiskFull,
+    #[error("Download was cancelled")]
+    Cancelled,
+    #[error("Downloaded file failed verification: {0}")]
+    VerificationFailed(String),
+    #[error("Failed to start download process: {0}")]
+    Spawn(String),
+    #[error("Crashed during processing and could not be resumed")]
+    Crashed,
+    #[error("Download failed: {0}")]
+    Unknown(String),
+}
+
+const GEO_MARKERS: &[&str] = &[
+    "available in your country",
+    "blocked it in your country",
+    "geo restriction",
+    "geo-restrict",
+];
+const LOGIN_MARKERS: &[&str] = &[
+    "sign in to confirm",
+    "login required",
+    "requires authentication",
+    "only available for registered users",
+    "use --cookies",
+    "http error 401",
+];
+const UNAVAILABLE_MARKERS: &[&str] = &[
+    "private video",
+    "video unavailable",
+    "has been removed",
+    "no longer available",
+    "account associated with this video has been terminated",
+    "http error 404",
+    "http error 410",
+];
+const UNSUPPORTED_MARKERS: &[&str] = &["unsupported url", "is not a valid url"];
+const DISK_FULL_MARKERS: &[&str] = &["no space left on device", "errno 28", "disk full"];
+const FFMPEG_MARKERS: &[&str] = &[
+    "postprocessing:",
+    "ffmpeg not found",
+    "ffmpeg exited with code",
+    "conversion failed",
+];
+const NETWORK_MARKERS: &[&str] = &[
+    "timed out",
+    "connection reset",
+    "connection refused",
+    "connection aborted",
+    "network is unreachable",
+    "temporary failure in name resolution",
+    "getaddrinfo failed",
+    "incompleteread",
+    "unable to download webpage",
+    "unable to download video data",
+    "giving up after",
+];
+
+impl DownloadError {
+    /// Classifies the stderr tail of a failed yt-dlp/ffmpeg run.
+    pub fn from_stderr(stderr: &str) -> Self {
+        let haystack = stderr.to_lowercase();
+        let matches = |markers: &[&str]| markers.iter().any(|m| haystack.contains(m));
+
+        // Order matters: specific causes win over the generic network wording
+        // yt-dlp wraps around them (e.g. "Unable to download webpage: HTTP Error 404").
+        if matches(GEO_MARKERS) {
+            DownloadError::GeoBlocked
+        } else if matches(LOGIN_MARKERS) {
+            DownloadError::LoginRequired
+        } else if matches(UNAVAILABLE_MARKERS) {
+            DownloadError::Unavailable
+        } else if matches(UNSUPPORTED_MARKERS) {
+            DownloadError::UnsupportedUrl
+        } else if matches(DISK_FULL_MARKERS) {
+            DownloadError::DiskFull
+        } else if matches(FFMPEG_MARKERS) {
+            DownloadError::Ffmpeg(error_detail(stderr))
+        } else if let Some(status) = http_status(&haystack)
+            .or_else(|| haystack.contains("too many requests").then_some(429))
+        {
+            DownloadError::Http(status)
+        } else if matches(NETWORK_MARKERS) {
+            DownloadError::Network(error_detail(stderr))
+        } else {
+            DownloadError::Unknown(error_detail(stderr))
+        }
+    }
+
+    /// Machine-readable identifier, identical to the serialized `code` tag.
+    pub fn code(&self) -> &'static str {
+        match self {
+            DownloadError::Network(_) => "network",
+            DownloadError::Http(_) => "http",
+            DownloadError::GeoBlocked => "geo_blocked",
+            DownloadError::LoginRequired => "login_required",
+            DownloadError::Unavailable => "unavailable",
+            DownloadError::UnsupportedUrl => "unsupported_url",
+            DownloadError::Ffmpeg(_) => "ffmpeg",
+            DownloadError::DiskFull => "disk_full",
+            DownloadError::Cancelled => "cancelled",
+            DownloadError::VerificationFailed(_) => "verification_failed",
+            DownloadError::Spawn(_) => "spawn",
+            DownloadError::Crashed => "crashed",
+            DownloadError::Unknown(_) => "unknown",
+        }
+    }
+
+    pub fn class(&self) -> FailureClass {
+        match self {
+            DownloadError::Network(_) => FailureClass::Network,
+            DownloadError::Http(429) => FailureClass::RateLimited,
+            DownloadError::Http(500..=599) => FailureClass::ServerError,
+            DownloadError::Unknown(_) => FailureClass::Unknown,
+            _ => FailureClass::Fatal,
+        }
+    }
+}
+
+fn http_status(haystack: &str) -> Option<u16> {
+    let rest = &haystack[haystack.find("http error ")? + "http error ".len()..];
+    rest.get(..3)?.parse().ok()
+}
+
+/// The first `ERROR:` line yt-dlp printed, or the last line if there is none.
+fn error_detail(stderr: &str) -> String {
+    let mut lines = stderr.lines().map(str::trim).filter(|l| !l.is_empty());
+    lines
+        .clone()
+        .find_map(|l| l.split_once("ERROR:").map(|(_, msg)| msg.trim().to_string()))
+        .or_else(|| lines.next_back().map(str::to_string))
+        .unwrap_or_else(|| "yt-dlp exited with an error".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_yt_dlp_stderr() {
+        let cases: &[(&str, DownloadError)] = &[
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: The uploader has not made this video available in your country",
+                DownloadError::GeoBlocked,
+            ),
+            // Geo-blocking wins over the HTTP status it comes with
+            (
+                "ERROR: unable to download video data: HTTP Error 403: Forbidden\nERROR: [BBC] p0abc: This video is not available in your country due to geo restriction",
+                DownloadError::GeoBlocked,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Sign in to confirm you're not a bot. Use --cookies-from-browser or --cookies for the authentication.",
+                DownloadError::LoginRequired,
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Sign in to confirm your age. This video may be inappropriate for some users.",
+                DownloadError::LoginRequired,
+            ),
+            ("ERROR: [vimeo] 123: HTTP Error 401: Unauthorized", DownloadError::LoginRequired),
+            // The sign-in hint on private videos is not a login prompt
+            ("ERROR: [youtube] dQw4w9WgXcQ: Private video. Sign in if you've been granted access to this video", DownloadError::Unavailable),
+            ("ERROR: [youtube] dQw4w9WgXcQ: Video unavailable. This video has been removed by the uploader", DownloadError::Unavailable),
+            // Wrapped in the generic network wording, but the status is what counts
+            ("ERROR: [generic] Unable to download webpage: HTTP Error 404: Not Found (caused by <HTTPError 404: Not Found>)", DownloadError::Unavailable),
+            ("ERROR: Unsupported URL: https://example.com/page", DownloadError::UnsupportedUrl),
+            ("ERROR: unable to write data: [Errno 28] No space left on device", DownloadError::DiskFull),
+            ("ERROR: Postprocessing: Conversion failed!", DownloadError::Ffmpeg("Postprocessing: Conversion failed!".to_string())),
+            ("ERROR: unable to download video data: HTTP Error 403: Forbidden", DownloadError::Http(403)),
+            ("ERROR: unable to download video data: HTTP Error 503: Service Unavailable", DownloadError::Http(503)),
+            ("ERROR: [youtube] dQw4w9WgXcQ: HTTP Error 429: Too Many Requests", DownloadError::Http(429)),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Unable to download API page: <urlopen error [Errno -3] Temporary failure in name resolution> (caused by TransportError)",
+                DownloadError::Network("[youtube] dQw4w9WgXcQ: Unable to download API page: <urlopen error [Errno -3] Temporary failure in name resolution> (caused by TransportError)".to_string()),
+            ),
+            (
+                "[download] Got error: The read operation timed out. Retrying (1/10)...\nERROR: The read operation timed out",
+                DownloadError::Network("The read operation timed out".to_string()),
+            ),
+            ("something odd happened", DownloadError::Unknown("something odd happened".to_string())),
+            ("", DownloadError::Unknown("yt-dlp exited with an error".to_string())),
+        ];
+        for (stderr, expected) in cases {
+            assert_eq!(&DownloadError::from_stderr(stderr), expected, "stderr: {}", stderr);
+        }
+    }
+
+    #[test]
+    fn code_matches_the_serialized_tag() {
+        let errors = [
+            DownloadError::Network(String::new()),
+            DownloadError::Http(500),
+            DownloadError::GeoBlocked,
+            DownloadError::LoginRequired,
+            DownloadError::Unavailable,
+            DownloadError::UnsupportedUrl,
+            DownloadError::Ffmpeg(String::new()),
+            DownloadError::DiskFull,
+            DownloadError::Cancelled,
+            DownloadError::VerificationFailed(String::new()),
+            DownloadError::Spawn(String::new()),
+            DownloadError::Crashed,
+            DownloadError::Unknown(String::new()),
+        ];
+        for error in errors {
+            let json = serde_json::to_value(&error).unwrap();
+            assert_eq!(json["code"], error.code());
+        }
+    }
+
+    #[test]
+    fn class_decides_retries() {
+        let cases = [
+            (DownloadError::Network(String::new()), FailureClass::Network),
+            (DownloadError::Http(429), FailureClass::RateLimited),
+            (DownloadError::Http(500), FailureClass::ServerError),
+            (DownloadError::Http(503), FailureClass::ServerError),
+            (DownloadError::Http(403), FailureClass::Fatal),
+            (DownloadError::GeoBlocked, FailureClass::Fatal),
+            (DownloadError::LoginRequired, FailureClass::Fatal),
+            (DownloadError::DiskFull, FailureClass::Fatal),
+            (DownloadError::Unknown(String::new()), FailureClass::Unknown),
+        ];
+        for (error, class) in cases {
+            assert_eq!(error.class(), class, "{:?}", error);
+        }
+    }
+}
@@ -2,9 +2,21 @@ use tauri::{Manager, Emitter};
 use tauri_plugin_shell::ShellExt;
 use std::sync::{Arc, Mutex};
 use std::fs;
+mod archive;
+mod cleanup;
 mod commands;
+mod conflict;
 mod download;
+mod error;
 mod persistence;
+mod playlist;
+mod process;
+mod retry;
+mod routing;
+mod schedule;
+mod settings;
+mod sqlite_store;
+mod template;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -30,29 +42,68 @@ pub fn run() {
                 let _ = fs::create_dir_all(&app_data_dir);
             }
             let persistence = persistence::PersistenceManager::new(app_data_dir);
+
+            let settings = persistence.settings();
+            let manager = app.state::<download::DownloadManager>();
+            if let Err(e) = manager.set_max_concurrent(settings.max_concurrent) {
+                log::warn!("[SETTINGS] Ignoring stored max_concurrent: {}", e);
+            }
+            if let Err(e) = manager.set_host_limits(settings.host_limits) {
+                log::warn!("[SETTINGS] Ignoring stored host limits: {}", e);
+            }
+            if let Err(e) = manager.set_bandwidth_limits(settings.bandwidth) {
+                log::warn!("[SETTINGS] Ignoring stored bandwidth limits: {}", e);
+            }
+            if let Err(e) = manager.set_schedule(settings.schedule) {
+                log::warn!("[SETTINGS] Ignoring stored schedule: {}", e);
+            }
+            if let Err(e) = manager.set_pause_strategy(settings.pause_strategy) {
+                log::warn!("[SETTINGS] Ignoring stored pause strategy: {}", e);
+            }
+            if let Err(e) = manager.set_disk_space(settings.disk_space) {
+                log::warn!("[SETTINGS] Ignoring stored disk space guards: {}", e);
+            }
+            if let Err(e) = manager.set_output_template(settings.output_template) {
+                log::warn!("[SETTINGS] Ignoring stored output template: {}", e);
+            }
+            if let Err(e) = manager.set_routing_rules(settings.routing_rules) {
+                log::warn!("[SETTINGS] Ignoring stored routing rules: {}", e);
+            }
+            manager.set_conflict_policy(settings.conflict_policy);
+            manager.set_archive_policy(settings.archive_policy);
+            if let Err(e) = manager.set_retry_policy(settings.retry_policy) {
+                log::warn!("[SETTINGS] Ignoring stored retry policy: {}", e);
+            }
             
-            // Crash Recovery: Load tasks and handle non-terminal states
+            // Crash Recovery: Re-queue unfinished work, fail only what cannot be resumed
             if let Ok(persisted_tasks) = persistence.load_tasks() {
                 let manager = app.state::<download::DownloadManager>();
                 let mut tasks = manager.tasks.lock().unwrap();
-                for pt in persisted_tasks {
-                    let status = if matches!(pt.status, download::DownloadStatus::Completed | download::DownloadStatus::Cancelled | download::DownloadStatus::Error) {
-                        pt.status
-                    } else {
-                        download::DownloadStatus::Error // Crashed during work
-                    };
-                    
-                    let mut task = download::DownloadTask::new(pt.id.clone(), pt.url.clone(), pt.title.clone());
-                    task.status = status;
-                    task.progress = pt.progress;
-                    task.final_path = pt.download_dir.map(std::path::PathBuf::from);
-                    
-                    tasks.insert(pt.id, Arc::new(Mutex::new(task)));
+                for pt in persisted_tasks.into_iter().filter(|pt| !pt.archived) {
+                    let task = pt.into_task();
+                    tasks.insert(task.id.clone(), Arc::new(Mutex::new(task)));
                 }
+                drop(tasks);
+                manager.restore_playlists();
             }
             
             app.manage(persistence);
 
+            // Resume the durable queue
+            app.state::<download::DownloadManager>().process_queue(app.handle().clone());
+            download::DownloadManager::spawn_schedule_timer(app.handle().clone());
+            download::DownloadManager::spawn_disk_monitor(app.handle().clone());
+            download::DownloadManager::spawn_playlist_monitor(app.handle().clone());
+
+            // Report fragments a crash or forced quit left behind; deleting them needs confirmation
+            let sweep_handle = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                let report = sweep_handle.state::<download::DownloadManager>().scan_orphaned_files(&sweep_handle);
+                if !report.files.is_empty() {
+                    let _ = sweep_handle.emit("orphaned-files", report);
+                }
+            });
+
             // Binary Detection & Capability Checks
             let app_handle = app.handle().clone();
             
@@ -64,7 +115,7 @@ pub fn run() {
                         match cmd.args(["--version"]).output().await {
                             Ok(output) if output.status.success() => {
                                 let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                                if version < "2023.01.01".to_string() {
+                                if version.as_str() < "2023.01.01" {
                                      let _ = app_handle.emit("binary-error", format!("yt-dlp version {} is too old. Please update to at least 2023.01.01.", version));
                                 }
                             },
@@ -92,6 +143,12 @@ pub fn run() {
             let tray_menu = tauri::menu::Menu::with_items(app, &[
                 &tauri::menu::MenuItem::with_id(app, "show", "Show VidFlow", true, None::<&str>)?,
                 &tauri::menu::PredefinedMenuItem::separator(app)?,
+                &tauri::menu::MenuItem::with_id(app, "pause_all", "Pause All", true, None::<&str>)?,
+                &tauri::menu::MenuItem::with_id(app, "resume_all", "Resume All", true, None::<&str>)?,
+                &tauri::menu::MenuItem::with_id(app, "retry_failed", "Retry Failed", true, None::<&str>)?,
+                &tauri::menu::MenuItem::with_id(app, "cancel_queued", "Cancel Queued", true, None::<&str>)?,
+                &tauri::menu::MenuItem::with_id(app, "clear_completed", "Clear Completed", true, None::<&str>)?,
+                &tauri::menu::PredefinedMenuItem::separator(app)?,
                 &tauri::menu::MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?,
             ])?;
 
@@ -105,6 +162,21 @@ pub fn run() {
                             let _ = window.show();
                             let _ = window.set_focus();
                         }
+                        "pause_all" => {
+                            app.state::<download::DownloadManager>().pause_all(app);
+                        }
+                        "resume_all" => {
+                            app.state::<download::DownloadManager>().resume_all(app);
+                        }
+                        "retry_failed" => {
+                            app.state::<download::DownloadManager>().retry_failed(app);
+                        }
+                        "cancel_queued" => {
+                            app.state::<download::DownloadManager>().cancel_queued(app);
+                        }
+                        "clear_completed" => {
+                            app.state::<download::DownloadManager>().clear_completed(app);
+                        }
                         "quit" => {
                             app.exit(0);
                         }
@@ -120,19 +192,59 @@ pub fn run() {
             commands::cancel_download,
             commands::pause_download,
             commands::resume_download,
+            commands::retry_download,
+            commands::pause_all,
+            commands::resume_all,
+            commands::cancel_queued,
+            commands::retry_failed,
+            commands::get_playlist_page,
+            commands::stream_playlist,
+            commands::cancel_playlist_stream,
+            commands::enqueue_playlist,
+            commands::list_playlists,
+            commands::cancel_playlist,
+            commands::retry_playlist,
+            commands::clear_completed,
+            commands::remove_download,
+            commands::clear_history,
+            commands::scan_orphaned_files,
+            commands::delete_orphaned_files,
+            commands::get_queue,
+            commands::set_priority,
+            commands::reorder_download,
+            commands::move_download_to_top,
+            commands::move_download_to_bottom,
+            commands::get_retry_policy,
+            commands::set_retry_policy,
+            commands::get_settings,
+            commands::set_max_concurrent,
+            commands::set_host_limits,
+            commands::set_bandwidth_limits,
+            commands::set_pause_strategy,
+            commands::set_disk_space,
+            commands::set_conflict_policy,
+            commands::set_archive_policy,
+            commands::import_download_archive,
+            commands::export_download_archive,
+            commands::set_output_template,
+            commands::preview_output_template,
+            commands::set_routing_rules,
+            commands::resolve_output_dir,
+            commands::set_schedule,
+            commands::start_download_now,
             commands::get_video_metadata,
             commands::list_downloads,
+            commands::query_history,
             commands::show_in_folder,
             commands::get_available_space
         ])
         .build(tauri::generate_context!())
         .expect("error while building tauri application");
 
-    app.run(|app_handle, event| match event {
-        tauri::RunEvent::Exit => {
+    app.run(|app_handle, event| {
+        if let tauri::RunEvent::Exit = event {
             let manager = app_handle.state::<download::DownloadManager>();
             manager.cleanup_all();
         }
-        _ => {}
     });
 }
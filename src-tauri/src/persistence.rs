@@ -1,11 +1,15 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use crate::download::{DownloadTask, DownloadStatus};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use crate::archive::DownloadArchive;
+use crate::download::{now_millis, url_host, DownloadOptions, DownloadTask, DownloadStatus, Priority};
+use crate::error::DownloadError;
+use crate::settings::AppSettings;
+use crate::sqlite_store::SqliteTaskStore;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PersistedTask {
     pub id: String,
     pub url: String,
@@ -13,6 +17,32 @@ pub struct PersistedTask {
     pub title: String,
     pub progress: f64,
     pub download_dir: Option<String>,
+    // Absent in files written before per-task options were stored
+    #[serde(default)]
+    pub options: Option<DownloadOptions>,
+    #[serde(default)]
+    pub retry_count: u32,
+    #[serde(default)]
+    pub error: Option<DownloadError>,
+    #[serde(default)]
+    pub created_at: u64,
+    #[serde(default)]
+    pub started_at: Option<u64>,
+    #[serde(default)]
+    pub finished_at: Option<u64>,
+    #[serde(default)]
+    pub file_size: Option<u64>,
+    #[serde(default)]
+    pub avg_speed: Option<u64>,
+    #[serde(default)]
+    pub extractor: Option<String>,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default)]
+    pub queue_position: i64,
+    /// Cleared from the download list; kept only for history.
+    #[serde(default)]
+    pub archived: bool,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -21,33 +51,217 @@ pub struct PersistenceData {
     pub tasks: Vec<PersistedTask>,
 }
 
-pub struct PersistenceManager {
-    path: PathBuf,
+impl PersistedTask {
+    pub fn from_task(task: &DownloadTask) -> Self {
+        Self {
+            id: task.id.clone(),
+            url: task.url.clone(),
+            status: task.status.clone(),
+            title: task.title.clone(),
+            progress: task.progress,
+            download_dir: task.final_path.as_ref().map(|p| p.to_string_lossy().to_string()),
+            options: Some(task.options.clone()),
+            retry_count: task.retry_count,
+            error: task.error.clone(),
+            created_at: task.created_at,
+            started_at: task.started_at,
+            finished_at: task.finished_at,
+            file_size: task.file_size,
+            avg_speed: task.average_speed(),
+            extractor: task.extractor.clone(),
+            priority: task.priority,
+            queue_position: task.queue_position,
+            archived: false,
+        }
+    }
+
+    /// Rebuilds a task after a restart. Work that was queued or in flight goes
+    /// back into the queue and continues from its `.part` file; paused work
+    /// stays paused until resumed. Either only fails when the options it was
+    /// started with are gone.
+    pub fn into_task(self) -> DownloadTask {
+        let recoverable = match &self.options {
+            Some(options) => options.output_dir.as_ref().is_none_or(|dir| Path::new(dir).is_dir()),
+            None => false,
+        };
+
+        let mut task = DownloadTask::new(self.id, self.url, self.title, self.options.unwrap_or_default());
+        task.progress = self.progress;
+        task.final_path = self.download_dir.map(PathBuf::from);
+        task.retry_count = self.retry_count;
+        task.error = self.error;
+        task.created_at = self.created_at;
+        task.started_at = self.started_at;
+        task.finished_at = self.finished_at;
+        task.file_size = self.file_size;
+        task.extractor = self.extractor;
+        task.priority = self.priority;
+        task.queue_position = self.queue_position;
+
+        match self.status {
+            DownloadStatus::Completed | DownloadStatus::Cancelled | DownloadStatus::Error => {
+                task.status = self.status;
+            }
+            // No process survives a restart, so resuming relaunches it
+            DownloadStatus::Paused if recoverable => {
+                task.status = DownloadStatus::Paused;
+            }
+            _ if recoverable => {
+                task.status = DownloadStatus::Queued;
+            }
+            _ => {
+                task.status = DownloadStatus::Error;
+                task.error = Some(DownloadError::Crashed);
+            }
+        }
+        task
+    }
 }
 
-impl PersistenceManager {
-    pub fn new(app_dir: PathBuf) -> Self {
-        let path = app_dir.join("tasks.json");
-        Self { path }
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct HistoryQuery {
+    #[serde(default)]
+    pub statuses: Vec<DownloadStatus>,
+    /// Matches the extractor name exactly or the host as a substring.
+    pub site: Option<String>,
+    /// Unix milliseconds, inclusive, compared against the finish time (or creation time if unfinished).
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    /// Case-insensitive substring of the title or URL.
+    pub search: Option<String>,
+    #[serde(default)]
+    pub offset: u32,
+    pub limit: Option<u32>,
+}
+
+impl HistoryQuery {
+    pub const MAX_LIMIT: u32 = 200;
+
+    pub fn effective_limit(&self) -> u32 {
+        self.limit.unwrap_or(50).clamp(1, Self::MAX_LIMIT)
     }
 
-    pub fn save_tasks(&self, tasks: &HashMap<String, Arc<Mutex<DownloadTask>>>) -> Result<(), String> {
-        let mut persisted_tasks = Vec::new();
-        for task_arc in tasks.values() {
-            let task = task_arc.lock().unwrap();
-            persisted_tasks.push(PersistedTask {
-                id: task.id.clone(),
-                url: task.url.clone(),
-                status: task.status.clone(),
-                title: task.title.clone(),
-                progress: task.progress,
-                download_dir: task.final_path.as_ref().map(|p| p.to_string_lossy().to_string()),
-            });
+    pub fn matches(&self, task: &PersistedTask) -> bool {
+        if !self.statuses.is_empty() && !self.statuses.contains(&task.status) {
+            return false;
         }
+        if let Some(site) = self.site.as_deref().map(str::to_lowercase) {
+            let extractor_match = task.extractor.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(&site));
+            let host_match = url_host(&task.url).is_some_and(|h| h.contains(&site));
+            if !extractor_match && !host_match {
+                return false;
+            }
+        }
+        let when = task.finished_at.unwrap_or(task.created_at);
+        if self.from.is_some_and(|from| when < from) || self.to.is_some_and(|to| when > to) {
+            return false;
+        }
+        if let Some(search) = self.search.as_deref().map(str::to_lowercase) {
+            if !task.title.to_lowercase().contains(&search) && !task.url.to_lowercase().contains(&search) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A finished or in-flight task as shown on the History page.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct HistoryRecord {
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    pub status: DownloadStatus,
+    pub site: Option<String>,
+    pub extractor: Option<String>,
+    pub format_spec: Option<String>,
+    pub final_path: Option<String>,
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub file_size: Option<u64>,
+    pub avg_speed: Option<u64>,
+    pub error_code: Option<String>,
+    pub error_message: Option<String>,
+    pub attempts: u32,
+}
+
+impl From<PersistedTask> for HistoryRecord {
+    fn from(task: PersistedTask) -> Self {
+        Self {
+            site: url_host(&task.url),
+            format_spec: task.options.and_then(|o| o.format_spec),
+            error_code: task.error.as_ref().map(|e| e.code().to_string()),
+            error_message: task.error.as_ref().map(|e| e.to_string()),
+            attempts: task.retry_count + 1,
+            id: task.id,
+            url: task.url,
+            title: task.title,
+            status: task.status,
+            extractor: task.extractor,
+            final_path: task.download_dir,
+            created_at: task.created_at,
+            started_at: task.started_at,
+            finished_at: task.finished_at,
+            file_size: task.file_size,
+            avg_speed: task.avg_speed,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct HistoryPage {
+    pub total: u64,
+    pub records: Vec<HistoryRecord>,
+}
+
+/// Storage backend for task records. Writes are per task so a single status
+/// change never rewrites the whole history.
+pub trait TaskStore: Send + Sync {
+    fn load_tasks(&self) -> Result<Vec<PersistedTask>, String>;
+    fn upsert_tasks(&self, tasks: &[PersistedTask]) -> Result<(), String>;
+
+    fn upsert_task(&self, task: &PersistedTask) -> Result<(), String> {
+        self.upsert_tasks(std::slice::from_ref(task))
+    }
+
+    fn delete_tasks(&self, ids: &[String]) -> Result<(), String>;
+
+    /// The stored records among `ids`. Stores that can look up by id should override this.
+    fn load_tasks_by_id(&self, ids: &[String]) -> Result<Vec<PersistedTask>, String> {
+        Ok(self.load_tasks()?.into_iter().filter(|t| ids.contains(&t.id)).collect())
+    }
+
+    /// Newest first. Stores that can filter natively should override this.
+    fn query_history(&self, query: &HistoryQuery) -> Result<HistoryPage, String> {
+        let mut matching: Vec<PersistedTask> = self.load_tasks()?.into_iter().filter(|t| query.matches(t)).collect();
+        matching.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+        let total = matching.len() as u64;
+        let records = matching
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.effective_limit() as usize)
+            .map(HistoryRecord::from)
+            .collect();
+        Ok(HistoryPage { total, records })
+    }
+}
+
+/// The original `tasks.json` format. Kept as a fallback when the database
+/// cannot be opened and as the source for the one-time import.
+pub struct JsonTaskStore {
+    path: PathBuf,
+}
+
+impl JsonTaskStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
 
+    fn write_all(&self, tasks: Vec<PersistedTask>) -> Result<(), String> {
         let data = PersistenceData {
             version: 1,
-            tasks: persisted_tasks,
+            tasks,
         };
 
         let json = serde_json::to_string_pretty(&data).map_err(|e| e.to_string())?;
@@ -59,8 +273,10 @@ impl PersistenceManager {
         
         Ok(())
     }
+}
 
-    pub fn load_tasks(&self) -> Result<Vec<PersistedTask>, String> {
+impl TaskStore for JsonTaskStore {
+    fn load_tasks(&self) -> Result<Vec<PersistedTask>, String> {
         if !self.path.exists() {
             return Ok(Vec::new());
         }
@@ -78,4 +294,147 @@ impl PersistenceManager {
             }
         }
     }
+
+    fn upsert_tasks(&self, tasks: &[PersistedTask]) -> Result<(), String> {
+        // A flat file cannot be patched in place, so merge and rewrite
+        let mut existing = self.load_tasks()?;
+        let mut index: HashMap<String, usize> = existing.iter().enumerate().map(|(i, t)| (t.id.clone(), i)).collect();
+        for task in tasks {
+            match index.get(&task.id) {
+                Some(&i) => existing[i] = task.clone(),
+                None => {
+                    index.insert(task.id.clone(), existing.len());
+                    existing.push(task.clone());
+                }
+            }
+        }
+        self.write_all(existing)
+    }
+
+    fn delete_tasks(&self, ids: &[String]) -> Result<(), String> {
+        let mut existing = self.load_tasks()?;
+        existing.retain(|t| !ids.contains(&t.id));
+        self.write_all(existing)
+    }
+}
+
+pub struct PersistenceManager {
+    store: Box<dyn TaskStore>,
+    archive: DownloadArchive,
+    settings_path: PathBuf,
+    settings: Mutex<AppSettings>,
+}
+
+impl PersistenceManager {
+    pub fn new(app_dir: PathBuf) -> Self {
+        let json_path = app_dir.join("tasks.json");
+        let store: Box<dyn TaskStore> = match SqliteTaskStore::open(&app_dir.join("vidflow.db")) {
+            Ok(store) => {
+                if let Err(e) = import_legacy_json(&store, &json_path) {
+                    log::error!("[PERSISTENCE] Failed to import {}: {}", json_path.display(), e);
+                }
+                Box::new(store)
+            }
+            Err(e) => {
+                log::error!("[PERSISTENCE] Could not open task database, falling back to JSON: {}", e);
+                Box::new(JsonTaskStore::new(json_path))
+            }
+        };
+        let settings_path = app_dir.join("settings.json");
+        let settings = load_settings(&settings_path).unwrap_or_else(|e| {
+            log::error!("[PERSISTENCE] Failed to load settings, using defaults: {}", e);
+            AppSettings::default()
+        });
+        let archive = DownloadArchive::open(app_dir.join("archive.txt"));
+        Self { store, archive, settings_path, settings: Mutex::new(settings) }
+    }
+
+    pub fn save_task(&self, task: &DownloadTask) -> Result<(), String> {
+        self.store.upsert_task(&PersistedTask::from_task(task))
+    }
+
+    pub fn save_tasks(&self, tasks: &[PersistedTask]) -> Result<(), String> {
+        self.store.upsert_tasks(tasks)
+    }
+
+    pub fn load_tasks(&self) -> Result<Vec<PersistedTask>, String> {
+        self.store.load_tasks()
+    }
+
+    pub fn delete_tasks(&self, ids: &[String]) -> Result<(), String> {
+        self.store.delete_tasks(ids)
+    }
+
+    pub fn load_tasks_by_id(&self, ids: &[String]) -> Result<Vec<PersistedTask>, String> {
+        self.store.load_tasks_by_id(ids)
+    }
+
+    /// Ids of every stored record matching `query`, ignoring its paging.
+    pub fn find_task_ids(&self, query: &HistoryQuery) -> Result<Vec<String>, String> {
+        let mut page_query = HistoryQuery { offset: 0, limit: Some(HistoryQuery::MAX_LIMIT), ..query.clone() };
+        let mut ids = Vec::new();
+        loop {
+            let page = self.store.query_history(&page_query)?;
+            let count = page.records.len();
+            ids.extend(page.records.into_iter().map(|r| r.id));
+            if count == 0 || ids.len() as u64 >= page.total {
+                return Ok(ids);
+            }
+            page_query.offset += count as u32;
+        }
+    }
+
+    pub fn query_history(&self, query: &HistoryQuery) -> Result<HistoryPage, String> {
+        self.store.query_history(query)
+    }
+
+    pub fn archive(&self) -> &DownloadArchive {
+        &self.archive
+    }
+
+    pub fn settings(&self) -> AppSettings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    /// Applies `change` and writes the result; the in-memory copy is only
+    /// replaced once the file is safely on disk.
+    pub fn update_settings(&self, change: impl FnOnce(&mut AppSettings)) -> Result<(), String> {
+        let mut settings = self.settings.lock().unwrap();
+        let mut next = settings.clone();
+        change(&mut next);
+
+        let json = serde_json::to_string_pretty(&next).map_err(|e| e.to_string())?;
+        let temp_path = self.settings_path.with_extension("json.tmp");
+        fs::write(&temp_path, json).map_err(|e| e.to_string())?;
+        fs::rename(&temp_path, &self.settings_path).map_err(|e| e.to_string())?;
+
+        *settings = next;
+        Ok(())
+    }
+}
+
+fn load_settings(path: &Path) -> Result<AppSettings, String> {
+    if !path.exists() {
+        return Ok(AppSettings::default());
+    }
+    let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse settings file: {}", e))
+}
+
+/// Moves records from a pre-database `tasks.json` into `store`, then renames
+/// the file so the import only ever runs once.
+fn import_legacy_json(store: &dyn TaskStore, json_path: &Path) -> Result<(), String> {
+    if !json_path.exists() {
+        return Ok(());
+    }
+    let mut tasks = JsonTaskStore::new(json_path.to_path_buf()).load_tasks()?;
+    // tasks.json never recorded when a task was added; date them to the import
+    let now = now_millis();
+    for task in tasks.iter_mut().filter(|t| t.created_at == 0) {
+        task.created_at = now;
+    }
+    store.upsert_tasks(&tasks)?;
+    fs::rename(json_path, json_path.with_extension("json.imported")).map_err(|e| e.to_string())?;
+    log::info!("[PERSISTENCE] Imported {} tasks from {}", tasks.len(), json_path.display());
+    Ok(())
 }
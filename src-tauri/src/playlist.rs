@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+use crate::download::{DownloadStatus, PlaylistEntry};
+
+/// Ties a child task to its playlist group. Kept in the child's options so
+/// groups can be rebuilt from the stored tasks after a restart.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlaylistRef {
+    pub group_id: String,
+    pub title: String,
+    pub url: String,
+    /// 1-based position in the playlist.
+    pub index: u32,
+}
+
+/// Which entries of a playlist to enqueue. With neither `items` nor
+/// `entry_ids`, every entry is taken.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlaylistSelection {
+    /// Playlist indexes in yt-dlp's `--playlist-items` syntax, e.g. `1-5,8,10-`.
+    pub items: Option<String>,
+    /// Entries picked one by one; combined with `items` when both are given.
+    pub entry_ids: Option<Vec<String>>,
+    /// Leave out entries that are already in the download archive.
+    pub only_new: bool,
+}
+
+/// A playlist as the frontend got it from `get_video_metadata`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaylistRequest {
+    pub url: String,
+    pub title: String,
+    /// Playlist id, used for routing rules that match on playlists.
+    #[serde(default)]
+    pub playlist_id: Option<String>,
+    pub entries: Vec<PlaylistEntry>,
+    #[serde(default)]
+    pub selection: PlaylistSelection,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistChild {
+    pub task_id: String,
+    pub entry_id: String,
+    pub index: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistEnqueued {
+    pub group_id: String,
+    /// Child tasks in playlist order.
+    pub children: Vec<PlaylistChild>,
+    /// Selected entries that were left out because they were downloaded before.
+    pub skipped: usize,
+}
+
+/// A slice of a playlist listing.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistPage {
+    pub url: String,
+    /// Entries before this page; the first entry has index `offset + 1`.
+    pub offset: u32,
+    pub entries: Vec<PlaylistEntry>,
+    /// Size of the whole playlist, when the site reports it.
+    pub total: Option<u64>,
+    pub has_more: bool,
+}
+
+/// Emitted as `playlist-page` for each page of a `stream_playlist` fetch.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaylistPagePayload {
+    pub stream_id: String,
+    pub page: Option<PlaylistPage>,
+    /// Set when the page could not be fetched; the stream ends with it.
+    pub error: Option<String>,
+    /// No more pages will follow.
+    pub done: bool,
+}
+
+/// The parent of a playlist's child tasks. It has no process of its own;
+/// its state is derived from the children.
+#[derive(Debug, Clone)]
+pub struct PlaylistGroup {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub task_ids: Vec<String>,
+}
+
+/// Emitted as `playlist-progress` whenever a group's aggregate changes.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PlaylistProgressPayload {
+    pub id: String,
+    pub title: String,
+    pub url: String,
+    pub status: DownloadStatus,
+    /// 0-100 over the entries that were not cancelled.
+    pub progress: f64,
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub task_ids: Vec<String>,
+}
+
+/// Parses `--playlist-items` style ranges: `3`, `2-5`, `7-` (to the end) or `-4` (from the start).
+pub fn parse_items(spec: &str) -> Result<Vec<RangeInclusive<u32>>, String> {
+    let index = |s: &str| -> Result<u32, String> {
+        match s.trim().parse::<u32>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("'{}' is not a playlist index; indexes start at 1", s.trim())),
+        }
+    };
+    spec.split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let range = match part.split_once('-') {
+                Some((start, end)) => {
+                    let start = if start.trim().is_empty() { 1 } else { index(start)? };
+                    let end = if end.trim().is_empty() { u32::MAX } else { index(end)? };
+                    start..=end
+                }
+                None => index(part).map(|n| n..=n)?,
+            };
+            if range.is_empty() {
+                return Err(format!("Playlist range '{}' is backwards", part));
+            }
+            Ok(range)
+        })
+        .collect()
+}
+
+/// The 1-based playlist position of the entry at `position` in the listing.
+pub fn entry_index(entry: &PlaylistEntry, position: usize) -> u32 {
+    entry.index.unwrap_or(position as u32 + 1)
+}
+
+/// Chosen entries with their playlist indexes.
+pub type Selected<'a> = Vec<(u32, &'a PlaylistEntry)>;
+
+/// Applies `selection` to `entries`, keeping playlist order. Returns the
+/// chosen entries with their indexes and how many were left out as already
+/// downloaded.
+pub fn select<'a>(
+    entries: &'a [PlaylistEntry],
+    selection: &PlaylistSelection,
+    archived: impl Fn(&str) -> bool,
+) -> Result<(Selected<'a>, usize), String> {
+    let ranges = selection.items.as_deref().map(parse_items).transpose()?;
+    let mut skipped = 0;
+    let chosen = entries
+        .iter()
+        .enumerate()
+        .map(|(position, entry)| (entry_index(entry, position), entry))
+        .filter(|(_, entry)| !entry.url.is_empty())
+        .filter(|(index, _)| ranges.as_ref().is_none_or(|r| r.iter().any(|r| r.contains(index))))
+        .filter(|(_, entry)| selection.entry_ids.as_ref().is_none_or(|ids| ids.contains(&entry.id)))
+        .filter(|(_, entry)| {
+            let known = selection.only_new && entry.archive_id.as_deref().is_some_and(&archived);
+            skipped += known as usize;
+            !known
+        })
+        .collect();
+    Ok((chosen, skipped))
+}
+
+/// Derives the group's state from `(status, progress)` of each remaining child.
+pub fn aggregate(group: &PlaylistGroup, children: &[(String, DownloadStatus, f64)]) -> PlaylistProgressPayload {
+    let count = |status: DownloadStatus| children.iter().filter(|(_, s, _)| *s == status).count();
+    let any = |statuses: &[DownloadStatus]| children.iter().any(|(_, s, _)| statuses.contains(s));
+    let completed = count(DownloadStatus::Completed);
+    let failed = count(DownloadStatus::Error);
+    let cancelled = count(DownloadStatus::Cancelled);
+
+    let status = if any(&[DownloadStatus::Preparing, DownloadStatus::Downloading, DownloadStatus::Merging]) {
+        DownloadStatus::Downloading
+    } else if any(&[DownloadStatus::Queued]) {
+        DownloadStatus::Queued
+    } else if any(&[DownloadStatus::Paused]) {
+        DownloadStatus::Paused
+    } else if cancelled == children.len() {
+        DownloadStatus::Cancelled
+    } else if failed > 0 {
+        DownloadStatus::Error
+    } else {
+        DownloadStatus::Completed
+    };
+
+    let counted: Vec<f64> = children
+        .iter()
+        .filter(|(_, s, _)| *s != DownloadStatus::Cancelled)
+        .map(|(_, s, p)| if *s == DownloadStatus::Completed { 100.0 } else { *p })
+        .collect();
+    let progress = if counted.is_empty() { 0.0 } else { counted.iter().sum::<f64>() / counted.len() as f64 };
+
+    PlaylistProgressPayload {
+        id: group.id.clone(),
+        title: group.title.clone(),
+        url: group.url.clone(),
+        status,
+        progress,
+        total: children.len(),
+        completed,
+        failed,
+        cancelled,
+        task_ids: children.iter().map(|(id, _, _)| id.clone()).collect(),
+    }
+}
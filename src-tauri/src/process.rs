@@ -0,0 +1,188 @@
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::async_runtime::{channel, Receiver, Sender};
+use tauri_plugin_shell::process::{CommandEvent, TerminatedPayload};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use crate::download::SYSTEM_GUARDRAILS;
+
+/// A download process started in its own process group, so helpers it
+/// launches (ffmpeg during merge) can be signalled together with it.
+#[derive(Debug)]
+pub struct ProcessHandle {
+    pid: u32,
+    /// Set once the group is known to be gone. Its id may be reused after
+    /// that, so nothing is signalled any more.
+    exited: Arc<AtomicBool>,
+}
+
+/// Spawns `program` and streams its output as the same events the shell
+/// plugin produces: one `Stdout`/`Stderr` per line, then `Terminated` once
+/// the whole process group has exited.
+pub fn spawn_in_group(program: &str, args: &[&str]) -> std::io::Result<(Receiver<CommandEvent>, ProcessHandle)> {
+    let mut command = tokio::process::Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    command.process_group(0);
+    #[cfg(windows)]
+    command.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let mut child = command.spawn()?;
+    let pid = child.id().unwrap_or_default();
+    let exited = Arc::new(AtomicBool::new(false));
+    let (tx, rx) = channel(64);
+
+    let stdout = child.stdout.take().map(|out| forward_lines(out, tx.clone(), CommandEvent::Stdout));
+    let stderr = child.stderr.take().map(|err| forward_lines(err, tx.clone(), CommandEvent::Stderr));
+
+    let group_exited = exited.clone();
+    tauri::async_runtime::spawn(async move {
+        let status = child.wait().await;
+        // The leader can exit while ffmpeg is still running in its group
+        #[cfg(unix)]
+        if group_alive(pid) {
+            let exited = group_exited.clone();
+            tokio::task::spawn_blocking(move || escalate(pid, &exited)).await.ok();
+        }
+        group_exited.store(true, Ordering::SeqCst);
+        // Pipes close once every writer in the group is gone
+        for reader in [stdout, stderr].into_iter().flatten() {
+            let _ = reader.await;
+        }
+
+        let payload = match status {
+            Ok(status) => TerminatedPayload {
+                code: status.code(),
+                #[cfg(unix)]
+                signal: std::os::unix::process::ExitStatusExt::signal(&status),
+                #[cfg(windows)]
+                signal: None,
+            },
+            Err(e) => {
+                log::warn!("[PROCESS] Lost track of process {}: {}", pid, e);
+                TerminatedPayload { code: None, signal: None }
+            }
+        };
+        let _ = tx.send(CommandEvent::Terminated(payload)).await;
+    });
+
+    Ok((rx, ProcessHandle { pid, exited }))
+}
+
+fn forward_lines<T: AsyncRead + Unpin + Send + 'static>(
+    stream: T,
+    tx: Sender<CommandEvent>,
+    wrap: fn(Vec<u8>) -> CommandEvent,
+) -> tauri::async_runtime::JoinHandle<()> {
+    tauri::async_runtime::spawn(async move {
+        let mut reader = BufReader::new(stream);
+        let mut buf = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut buf).await, Ok(n) if n > 0) {
+            // Progress bars redraw with \r; treat each redraw as its own line
+            for line in buf.split(|b| *b == b'\n' || *b == b'\r').filter(|l| !l.is_empty()) {
+                let _ = tx.send(wrap(line.to_vec())).await;
+            }
+            buf.clear();
+        }
+    })
+}
+
+impl ProcessHandle {
+    /// Stops the process tree in the background: SIGTERM to the group, then
+    /// SIGKILL if it is still around after the grace period. Returns at once,
+    /// so it is safe to call with task locks held. Completion is reported
+    /// through the `Terminated` event.
+    pub fn terminate(self) {
+        #[cfg(unix)]
+        tauri::async_runtime::spawn_blocking(move || escalate(self.pid, &self.exited));
+        #[cfg(windows)]
+        tauri::async_runtime::spawn(async move {
+            if !self.exited.load(Ordering::SeqCst) {
+                taskkill(self.pid).await;
+            }
+        });
+    }
+
+    /// Like `terminate`, but blocks until the tree is gone. Used on app exit,
+    /// when nothing would be left to finish a background escalation.
+    pub fn terminate_and_wait(self) {
+        #[cfg(unix)]
+        escalate(self.pid, &self.exited);
+        #[cfg(windows)]
+        if !self.exited.load(Ordering::SeqCst) {
+            tauri::async_runtime::block_on(taskkill(self.pid));
+        }
+    }
+
+    /// SIGSTOP/SIGCONT the whole group, so a merge in progress pauses too.
+    #[cfg(unix)]
+    pub fn suspend(&self) {
+        if !self.exited.load(Ordering::SeqCst) {
+            signal_group(self.pid, libc::SIGSTOP);
+        }
+    }
+
+    #[cfg(unix)]
+    pub fn resume(&self) {
+        if !self.exited.load(Ordering::SeqCst) {
+            signal_group(self.pid, libc::SIGCONT);
+        }
+    }
+}
+
+#[cfg(windows)]
+async fn taskkill(pid: u32) {
+    let _ = tokio::process::Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+}
+
+#[cfg(unix)]
+fn signal_group(pgid: u32, signal: libc::c_int) -> bool {
+    let Ok(pgid) = libc::pid_t::try_from(pgid) else {
+        return false;
+    };
+    // pgid 0 or 1 would address our own group or every process
+    // SAFETY: killpg only takes plain integers
+    pgid > 1 && unsafe { libc::killpg(pgid, signal) } == 0
+}
+
+#[cfg(unix)]
+fn group_alive(pgid: u32) -> bool {
+    signal_group(pgid, 0)
+}
+
+/// SIGTERM, wait up to the grace period, then SIGKILL whatever is left.
+/// Gives up as soon as the group is seen to be gone, by the probe here or by
+/// the task waiting on the process.
+#[cfg(unix)]
+fn escalate(pgid: u32, exited: &AtomicBool) {
+    let gone = || exited.load(Ordering::SeqCst) || !group_alive(pgid);
+    if gone() || !signal_group(pgid, libc::SIGTERM) {
+        return;
+    }
+    // A stopped group cannot act on SIGTERM until it is continued
+    signal_group(pgid, libc::SIGCONT);
+
+    let deadline = Instant::now() + Duration::from_millis(SYSTEM_GUARDRAILS.kill_grace_ms);
+    while Instant::now() < deadline {
+        if gone() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    if gone() {
+        return;
+    }
+    log::warn!("[PROCESS] Group {} ignored SIGTERM, sending SIGKILL", pgid);
+    signal_group(pgid, libc::SIGKILL);
+}
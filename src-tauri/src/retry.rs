@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Coarse bucket a `DownloadError` falls into for retry decisions.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    Network,
+    ServerError,
+    RateLimited,
+    Fatal,
+    Unknown,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RetryPolicy {
+    /// Total attempts per task, including the first one.
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// Fraction of the delay randomised in either direction (0.0 - 1.0).
+    pub jitter: f64,
+    pub retryable: Vec<FailureClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay_ms: 2_000,
+            max_delay_ms: 60_000,
+            jitter: 0.25,
+            retryable: vec![
+                FailureClass::Network,
+                FailureClass::ServerError,
+                FailureClass::RateLimited,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 || self.max_attempts > 10 {
+            return Err("max_attempts must be between 1 and 10".to_string());
+        }
+        if self.base_delay_ms > self.max_delay_ms {
+            return Err("base_delay_ms must not exceed max_delay_ms".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err("jitter must be between 0.0 and 1.0".to_string());
+        }
+        Ok(())
+    }
+
+    /// `retries_so_far` is the number of attempts already made after the first.
+    pub fn should_retry(&self, class: FailureClass, retries_so_far: u32) -> bool {
+        self.retryable.contains(&class) && retries_so_far.saturating_add(1) < self.max_attempts
+    }
+
+    /// Backoff for the given retry number. `sample` is a uniform value in [0, 1)
+    /// so the calculation stays deterministic for callers that supply their own.
+    pub fn delay_for(&self, retries_so_far: u32, sample: f64) -> Duration {
+        let factor = 1u64 << retries_so_far.min(20);
+        let delay = self.base_delay_ms.saturating_mul(factor).min(self.max_delay_ms) as f64;
+        let spread = delay * self.jitter;
+        let jittered = delay - spread + 2.0 * spread * sample.clamp(0.0, 1.0);
+        Duration::from_millis(jittered.max(0.0) as u64)
+    }
+
+    pub fn next_delay(&self, class: FailureClass, retries_so_far: u32) -> Option<Duration> {
+        if !self.should_retry(class, retries_so_far) {
+            return None;
+        }
+        // uuid v4 is already backed by the OS RNG; no need for another dependency.
+        // The low 62 bits are all random (version and variant bits sit above them).
+        const MANTISSA: u128 = (1 << 53) - 1;
+        let sample = (uuid::Uuid::new_v4().as_u128() & MANTISSA) as f64 / (1u64 << 53) as f64;
+        Some(self.delay_for(retries_so_far, sample))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay_ms: 1_000,
+            max_delay_ms: 5_000,
+            jitter: 0.5,
+            retryable: vec![FailureClass::Network, FailureClass::RateLimited],
+        }
+    }
+
+    #[test]
+    fn default_policy_is_valid() {
+        assert_eq!(RetryPolicy::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_values() {
+        let invalid = [
+            RetryPolicy { max_attempts: 0, ..policy() },
+            RetryPolicy { max_attempts: 11, ..policy() },
+            RetryPolicy { base_delay_ms: 6_000, ..policy() },
+            RetryPolicy { jitter: -0.1, ..policy() },
+            RetryPolicy { jitter: 1.5, ..policy() },
+        ];
+        for p in invalid {
+            assert!(p.validate().is_err(), "{:?} should be rejected", p);
+        }
+        assert_eq!(RetryPolicy { max_attempts: 10, jitter: 1.0, ..policy() }.validate(), Ok(()));
+    }
+
+    #[test]
+    fn should_retry_only_retryable_classes() {
+        let p = policy();
+        assert!(p.should_retry(FailureClass::Network, 0));
+        assert!(p.should_retry(FailureClass::RateLimited, 0));
+        assert!(!p.should_retry(FailureClass::ServerError, 0));
+        assert!(!p.should_retry(FailureClass::Fatal, 0));
+        assert!(!p.should_retry(FailureClass::Unknown, 0));
+    }
+
+    #[test]
+    fn should_retry_stops_when_attempts_are_used_up() {
+        let p = policy();
+        assert!(p.should_retry(FailureClass::Network, 1));
+        assert!(!p.should_retry(FailureClass::Network, 2));
+        assert!(!p.should_retry(FailureClass::Network, u32::MAX));
+        assert!(!RetryPolicy { max_attempts: 1, ..policy() }.should_retry(FailureClass::Network, 0));
+    }
+
+    #[test]
+    fn delay_doubles_without_jitter() {
+        let p = RetryPolicy { jitter: 0.0, ..policy() };
+        assert_eq!(p.delay_for(0, 0.7), Duration::from_millis(1_000));
+        assert_eq!(p.delay_for(1, 0.7), Duration::from_millis(2_000));
+        assert_eq!(p.delay_for(2, 0.7), Duration::from_millis(4_000));
+    }
+
+    #[test]
+    fn delay_is_capped_at_max_delay() {
+        let p = RetryPolicy { jitter: 0.0, ..policy() };
+        assert_eq!(p.delay_for(3, 0.0), Duration::from_millis(5_000));
+        assert_eq!(p.delay_for(u32::MAX, 0.0), Duration::from_millis(5_000));
+    }
+
+    #[test]
+    fn jitter_spreads_around_the_delay() {
+        let p = policy();
+        assert_eq!(p.delay_for(1, 0.0), Duration::from_millis(1_000));
+        assert_eq!(p.delay_for(1, 0.5), Duration::from_millis(2_000));
+        assert_eq!(p.delay_for(1, 1.0), Duration::from_millis(3_000));
+        // Out-of-range samples are clamped rather than widening the spread
+        assert_eq!(p.delay_for(1, -1.0), Duration::from_millis(1_000));
+        assert_eq!(p.delay_for(1, 2.0), Duration::from_millis(3_000));
+    }
+
+    #[test]
+    fn next_delay_stays_within_jitter_bounds() {
+        let p = policy();
+        for retries in 0..2 {
+            let base = p.delay_for(retries, 0.5).as_millis() as f64;
+            for _ in 0..200 {
+                let delay = p.next_delay(FailureClass::Network, retries).unwrap().as_millis() as f64;
+                assert!(delay >= base * (1.0 - p.jitter) && delay <= base * (1.0 + p.jitter), "{} outside {}", delay, base);
+            }
+        }
+        assert_eq!(p.next_delay(FailureClass::Network, 2), None);
+        assert_eq!(p.next_delay(FailureClass::Fatal, 0), None);
+    }
+
+    #[test]
+    fn next_delay_covers_the_whole_jitter_range() {
+        let p = policy();
+        let delays: Vec<u128> = (0..400).map(|_| p.next_delay(FailureClass::Network, 1).unwrap().as_millis()).collect();
+        // 1s..3s around the 2s delay; each outer quarter of the range gets hit
+        assert!(delays.iter().any(|&d| d < 1_500), "no delay in the low quarter");
+        assert!(delays.iter().any(|&d| d >= 2_500), "no delay in the high quarter");
+    }
+
+    #[test]
+    fn next_delay_never_exceeds_max_delay_plus_jitter() {
+        let p = RetryPolicy { max_attempts: 10, ..policy() };
+        let ceiling = p.max_delay_ms as f64 * (1.0 + p.jitter);
+        for _ in 0..200 {
+            let delay = p.next_delay(FailureClass::Network, 8).unwrap();
+            assert!(delay.as_millis() as f64 <= ceiling);
+        }
+    }
+}
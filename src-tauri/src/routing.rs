@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use crate::download::url_host;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    Audio,
+    Video,
+}
+
+/// Sends matching downloads to `output_dir`. Every non-empty criterion must
+/// match; an empty list or `None` matches anything.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct RoutingRule {
+    #[serde(default)]
+    pub name: String,
+    /// Hosts without `www.`; `youtube.com` also covers `music.youtube.com`.
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// yt-dlp extractor names, e.g. `youtube` or `soundcloud`.
+    #[serde(default)]
+    pub extractors: Vec<String>,
+    /// Channel or uploader names, or the `@handle`/id in a channel URL.
+    #[serde(default)]
+    pub channels: Vec<String>,
+    /// Playlist ids, e.g. the `list=` parameter of a YouTube URL.
+    #[serde(default)]
+    pub playlists: Vec<String>,
+    #[serde(default)]
+    pub media: Option<MediaKind>,
+    /// May start with `~` for the home directory.
+    pub output_dir: String,
+}
+
+/// What the frontend already knows about a URL from analysing it. Routing
+/// runs at enqueue time, before yt-dlp has reported anything.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct RouteContext {
+    pub extractor: Option<String>,
+    pub channel: Option<String>,
+    pub playlist: Option<String>,
+}
+
+/// Result of a routing dry run. Both are `None` when no rule matches and
+/// the download would go to its own folder.
+#[derive(Clone, Serialize, Debug)]
+pub struct RouteDecision {
+    pub output_dir: Option<String>,
+    /// Name of the matching rule.
+    pub rule: Option<String>,
+}
+
+/// The facts rules are matched against, normalised to lowercase.
+#[derive(Debug)]
+pub struct RouteInput {
+    host: Option<String>,
+    extractor: Option<String>,
+    channels: Vec<String>,
+    playlist: Option<String>,
+    media: MediaKind,
+}
+
+impl RouteInput {
+    pub fn new(url: &str, format_spec: Option<&str>, context: &RouteContext) -> Self {
+        let parsed = url::Url::parse(url).ok();
+        let lower = |s: &Option<String>| s.as_ref().map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty());
+
+        // Channel URLs look like /@handle, /channel/<id>, /c/<name> or /user/<name>
+        let mut channels: Vec<String> = lower(&context.channel).into_iter().collect();
+        if let Some(mut segments) = parsed.as_ref().and_then(|u| u.path_segments()) {
+            match segments.next() {
+                Some(handle) if handle.starts_with('@') => channels.push(handle.to_lowercase()),
+                Some("channel" | "c" | "user") => channels.extend(segments.next().map(str::to_lowercase)),
+                _ => {}
+            }
+        }
+        let list_param = parsed
+            .as_ref()
+            .and_then(|u| u.query_pairs().find(|(k, _)| k == "list").map(|(_, v)| v.to_lowercase()));
+
+        Self {
+            host: url_host(url),
+            // "youtube:tab" is still YouTube
+            extractor: lower(&context.extractor).map(|e| e.split(':').next().unwrap_or_default().to_string()),
+            channels,
+            playlist: lower(&context.playlist).or(list_param),
+            media: if format_spec == Some("audio") { MediaKind::Audio } else { MediaKind::Video },
+        }
+    }
+}
+
+fn any_of(wanted: &[String], matches: impl Fn(&str) -> bool) -> bool {
+    wanted.is_empty() || wanted.iter().any(|w| matches(&w.trim().to_lowercase()))
+}
+
+impl RoutingRule {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.output_dir.trim().is_empty() {
+            return Err(format!("Routing rule '{}' needs an output folder", self.name));
+        }
+        let criteria = [&self.hosts, &self.extractors, &self.channels, &self.playlists];
+        if criteria.iter().flat_map(|c| c.iter()).any(|c| c.trim().is_empty()) {
+            return Err(format!("Routing rule '{}' has an empty match value", self.name));
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, input: &RouteInput) -> bool {
+        let host = input.host.as_deref().unwrap_or_default();
+        any_of(&self.hosts, |h| {
+            let h = h.strip_prefix("www.").unwrap_or(h);
+            host == h || host.ends_with(&format!(".{}", h))
+        }) && any_of(&self.extractors, |e| input.extractor.as_deref() == Some(e))
+            && any_of(&self.channels, |c| input.channels.iter().any(|known| known == c || known.trim_start_matches('@') == c.trim_start_matches('@')))
+            && any_of(&self.playlists, |p| input.playlist.as_deref() == Some(p))
+            && self.media.is_none_or(|m| m == input.media)
+    }
+}
+
+pub fn validate_rules(rules: &[RoutingRule]) -> Result<(), String> {
+    rules.iter().try_for_each(RoutingRule::validate)
+}
+
+/// The first rule matching `input`, in the order the rules are listed.
+pub fn route<'a>(rules: &'a [RoutingRule], input: &RouteInput) -> Option<&'a RoutingRule> {
+    rules.iter().find(|r| r.matches(input))
+}
+
+/// The rule that decides where a download goes. A download started with an
+/// explicit folder keeps it, so no rule applies.
+pub fn route_download<'a>(rules: &'a [RoutingRule], input: &RouteInput, explicit_dir: Option<&str>) -> Option<&'a RoutingRule> {
+    if explicit_dir.is_some() {
+        return None;
+    }
+    route(rules, input)
+}
+
+/// Expands a leading `~` to `home`.
+pub fn expand_home(dir: &str, home: Option<&Path>) -> PathBuf {
+    match (dir.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+            home.join(rest.trim_start_matches(['/', '\\']))
+        }
+        _ => PathBuf::from(dir),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, output_dir: &str) -> RoutingRule {
+        RoutingRule {
+            name: name.to_string(),
+            hosts: Vec::new(),
+            extractors: Vec::new(),
+            channels: Vec::new(),
+            playlists: Vec::new(),
+            media: None,
+            output_dir: output_dir.to_string(),
+        }
+    }
+
+    fn input(url: &str, context: RouteContext) -> RouteInput {
+        RouteInput::new(url, None, &context)
+    }
+
+    fn names<'a>(rules: &'a [RoutingRule], input: &RouteInput) -> Option<&'a str> {
+        route(rules, input).map(|r| r.name.as_str())
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let rules = vec![
+            RoutingRule { hosts: vec!["soundcloud.com".into()], ..rule("sc", "/sc") },
+            RoutingRule { hosts: vec!["youtube.com".into()], ..rule("yt", "/yt") },
+            rule("catch-all", "/all"),
+            RoutingRule { hosts: vec!["youtube.com".into()], ..rule("yt-late", "/late") },
+        ];
+        let yt = input("https://www.youtube.com/watch?v=abc", RouteContext::default());
+        assert_eq!(names(&rules, &yt), Some("yt"));
+        let other = input("https://vimeo.com/123", RouteContext::default());
+        assert_eq!(names(&rules, &other), Some("catch-all"));
+        assert_eq!(names(&rules[..2], &other), None);
+    }
+
+    #[test]
+    fn hosts_cover_subdomains_but_not_lookalikes() {
+        let rules = vec![RoutingRule { hosts: vec!["www.YouTube.com".into()], ..rule("yt", "/yt") }];
+        for url in ["https://youtube.com/watch?v=a", "https://www.youtube.com/watch?v=a", "https://music.youtube.com/watch?v=a"] {
+            assert_eq!(names(&rules, &input(url, RouteContext::default())), Some("yt"), "{}", url);
+        }
+        for url in ["https://notyoutube.com/watch?v=a", "https://youtube.com.evil.net/a", "not a url"] {
+            assert_eq!(names(&rules, &input(url, RouteContext::default())), None, "{}", url);
+        }
+    }
+
+    #[test]
+    fn extractor_ignores_case_and_sub_extractor() {
+        let rules = vec![RoutingRule { extractors: vec!["YouTube".into()], ..rule("yt", "/yt") }];
+        let tab = RouteContext { extractor: Some("youtube:tab".into()), ..Default::default() };
+        assert_eq!(names(&rules, &input("https://www.youtube.com/@x", tab)), Some("yt"));
+        let sc = RouteContext { extractor: Some("soundcloud".into()), ..Default::default() };
+        assert_eq!(names(&rules, &input("https://soundcloud.com/x", sc)), None);
+        // Without analysis there is no extractor to match
+        assert_eq!(names(&rules, &input("https://www.youtube.com/watch?v=a", RouteContext::default())), None);
+    }
+
+    #[test]
+    fn channels_match_url_handles_ids_and_context() {
+        let rules = vec![
+            RoutingRule { channels: vec!["@SomeCreator".into()], ..rule("handle", "/h") },
+            RoutingRule { channels: vec!["UC123".into()], ..rule("id", "/id") },
+            RoutingRule { channels: vec!["Some Band".into()], ..rule("name", "/n") },
+        ];
+        let none = RouteContext::default;
+        assert_eq!(names(&rules, &input("https://www.youtube.com/@somecreator/videos", none())), Some("handle"));
+        assert_eq!(names(&rules, &input("https://www.youtube.com/channel/UC123", none())), Some("id"));
+        assert_eq!(names(&rules, &input("https://www.youtube.com/c/uc123", none())), Some("id"));
+        let band = RouteContext { channel: Some("  some band ".into()), ..Default::default() };
+        assert_eq!(names(&rules, &input("https://www.youtube.com/watch?v=a", band)), Some("name"));
+        // A handle given without `@` still matches the URL's
+        let bare = vec![RoutingRule { channels: vec!["somecreator".into()], ..rule("bare", "/b") }];
+        assert_eq!(names(&bare, &input("https://www.youtube.com/@SomeCreator", none())), Some("bare"));
+        assert_eq!(names(&rules, &input("https://www.youtube.com/watch?v=a", none())), None);
+    }
+
+    #[test]
+    fn playlists_match_list_param_or_context() {
+        let rules = vec![RoutingRule { playlists: vec!["PLabc".into()], ..rule("pl", "/pl") }];
+        let url = "https://www.youtube.com/watch?v=a&list=PLabc&index=2";
+        assert_eq!(names(&rules, &input(url, RouteContext::default())), Some("pl"));
+        let ctx = RouteContext { playlist: Some("plabc".into()), ..Default::default() };
+        assert_eq!(names(&rules, &input("https://www.youtube.com/watch?v=a", ctx)), Some("pl"));
+        // The analysed playlist takes the place of the URL's
+        let other = RouteContext { playlist: Some("PLother".into()), ..Default::default() };
+        assert_eq!(names(&rules, &input(url, other)), None);
+    }
+
+    #[test]
+    fn every_criterion_must_match() {
+        let rules = vec![
+            RoutingRule {
+                hosts: vec!["youtube.com".into()],
+                playlists: vec!["PLabc".into()],
+                media: Some(MediaKind::Audio),
+                ..rule("yt-audio", "/a")
+            },
+            RoutingRule { media: Some(MediaKind::Video), ..rule("video", "/v") },
+        ];
+        let url = "https://www.youtube.com/watch?v=a&list=PLabc";
+        let audio = RouteInput::new(url, Some("audio"), &RouteContext::default());
+        assert_eq!(names(&rules, &audio), Some("yt-audio"));
+        let video = RouteInput::new(url, Some("bestvideo+bestaudio"), &RouteContext::default());
+        assert_eq!(names(&rules, &video), Some("video"));
+        let elsewhere = RouteInput::new("https://vimeo.com/1", Some("audio"), &RouteContext::default());
+        assert_eq!(names(&rules, &elsewhere), None);
+    }
+
+    #[test]
+    fn explicit_output_dir_beats_the_rules() {
+        let rules = vec![rule("catch-all", "/all")];
+        let yt = input("https://www.youtube.com/watch?v=a", RouteContext::default());
+        assert!(route_download(&rules, &yt, Some("/chosen")).is_none());
+        assert_eq!(route_download(&rules, &yt, None).map(|r| r.name.as_str()), Some("catch-all"));
+    }
+
+    #[test]
+    fn validate_rejects_empty_folder_and_values() {
+        assert!(rule("ok", "~/Music").validate().is_ok());
+        assert!(rule("no folder", "  ").validate().is_err());
+        let blank = RoutingRule { channels: vec![" ".into()], ..rule("blank", "/x") };
+        assert!(validate_rules(&[rule("ok", "/x"), blank]).is_err());
+    }
+
+    #[test]
+    fn expand_home_only_touches_a_leading_tilde() {
+        let home = Path::new("/home/me");
+        assert_eq!(expand_home("~", Some(home)), PathBuf::from("/home/me"));
+        assert_eq!(expand_home("~/Music", Some(home)), PathBuf::from("/home/me/Music"));
+        assert_eq!(expand_home("~\\Music", Some(home)), PathBuf::from("/home/me/Music"));
+        assert_eq!(expand_home("~other/Music", Some(home)), PathBuf::from("~other/Music"));
+        assert_eq!(expand_home("/data/~/x", Some(home)), PathBuf::from("/data/~/x"));
+        assert_eq!(expand_home("~/Music", None), PathBuf::from("~/Music"));
+    }
+}
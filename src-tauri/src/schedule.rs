@@ -0,0 +1,201 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDateTime, TimeZone, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use crate::settings::BandwidthLimits;
+
+/// A recurring time-of-day range, in local time. `end` before `start` means
+/// the window runs past midnight; `days` then refers to the day it starts on.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ScheduleWindow {
+    /// Days the window opens on. Empty means every day.
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    /// "HH:MM", 24-hour.
+    pub start: String,
+    pub end: String,
+    /// Replaces the global bandwidth limits while the window is open.
+    #[serde(default)]
+    pub bandwidth: Option<BandwidthLimits>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct Schedule {
+    /// When disabled, downloads start at any time and the windows are ignored.
+    pub enabled: bool,
+    pub windows: Vec<ScheduleWindow>,
+}
+
+fn parse_hhmm(value: &str) -> Result<u32, String> {
+    let invalid = || format!("Invalid time '{}', expected HH:MM", value);
+    let (h, m) = value.trim().split_once(':').ok_or_else(invalid)?;
+    let (h, m): (u32, u32) = (h.parse().map_err(|_| invalid())?, m.parse().map_err(|_| invalid())?);
+    if h > 23 || m > 59 {
+        return Err(invalid());
+    }
+    Ok(h * 60 + m)
+}
+
+impl ScheduleWindow {
+    fn bounds(&self) -> (u32, u32) {
+        // Validated on the way in, so a parse failure here only means an empty window
+        (parse_hhmm(&self.start).unwrap_or(0), parse_hhmm(&self.end).unwrap_or(0))
+    }
+
+    fn opens_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    pub fn contains(&self, at: NaiveDateTime) -> bool {
+        let (start, end) = self.bounds();
+        let minute = at.hour() * 60 + at.minute();
+        let today = at.weekday();
+        if start == end {
+            // Equal bounds cover the whole day
+            self.opens_on(today)
+        } else if start < end {
+            self.opens_on(today) && (start..end).contains(&minute)
+        } else {
+            (self.opens_on(today) && minute >= start) || (self.opens_on(today.pred()) && minute < end)
+        }
+    }
+}
+
+impl Schedule {
+    pub fn validate(&self) -> Result<(), String> {
+        for window in &self.windows {
+            parse_hhmm(&window.start)?;
+            parse_hhmm(&window.end)?;
+            if let Some(bandwidth) = &window.bandwidth {
+                bandwidth.validate()?;
+            }
+        }
+        if self.enabled && self.windows.is_empty() {
+            return Err("An enabled schedule needs at least one window".to_string());
+        }
+        Ok(())
+    }
+
+    /// The first window open at `at`, or `None` when outside all of them.
+    pub fn active_window(&self, at: NaiveDateTime) -> Option<&ScheduleWindow> {
+        self.windows.iter().find(|w| w.contains(at))
+    }
+
+    /// Whether queued tasks may be started at `at`.
+    pub fn allows(&self, at: NaiveDateTime) -> bool {
+        !self.enabled || self.active_window(at).is_some()
+    }
+
+    /// Time until the next window opens or closes, capped at an hour so clock
+    /// and timezone changes are picked up. `None` when there is nothing to wait for.
+    pub fn until_next_boundary(&self, now: DateTime<Local>) -> Option<Duration> {
+        if !self.enabled {
+            return None;
+        }
+        let today = now.date_naive();
+        let next = (0..=7)
+            .filter_map(|offset| today.checked_add_signed(ChronoDuration::days(offset)))
+            .flat_map(|date| {
+                self.windows.iter().flat_map(move |w| {
+                    let (start, end) = w.bounds();
+                    [start, end].map(|minute| date.and_hms_opt(minute / 60, minute % 60, 0))
+                })
+            })
+            .flatten()
+            .filter_map(|naive| Local.from_local_datetime(&naive).earliest())
+            .filter(|at| *at > now)
+            .min()?;
+        let wait = (next - now).to_std().unwrap_or_default();
+        Some(wait.min(Duration::from_secs(3600)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        // January 2026; the 2nd is a Friday
+        NaiveDate::from_ymd_opt(2026, 1, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn window(days: &[Weekday], start: &str, end: &str) -> ScheduleWindow {
+        ScheduleWindow { days: days.to_vec(), start: start.to_string(), end: end.to_string(), bandwidth: None }
+    }
+
+    fn schedule(windows: Vec<ScheduleWindow>) -> Schedule {
+        Schedule { enabled: true, windows }
+    }
+
+    #[test]
+    fn over_midnight_window_spans_the_day_change() {
+        assert_eq!(at(2, 0, 0).weekday(), Weekday::Fri);
+        let night = window(&[], "23:00", "02:00");
+        assert!(!night.contains(at(2, 22, 59)));
+        assert!(night.contains(at(2, 23, 0)));
+        assert!(night.contains(at(3, 0, 30)));
+        assert!(night.contains(at(3, 1, 59)));
+        assert!(!night.contains(at(3, 2, 0)));
+    }
+
+    #[test]
+    fn weekday_mask_applies_to_the_opening_day() {
+        let friday_night = window(&[Weekday::Fri], "23:00", "02:00");
+        assert!(friday_night.contains(at(2, 23, 30)));
+        // Saturday morning is still Friday's window
+        assert!(friday_night.contains(at(3, 1, 0)));
+        assert!(!friday_night.contains(at(3, 23, 30)));
+        assert!(!friday_night.contains(at(4, 1, 0)));
+        // Thursday night's window would end on Friday morning, but Thursday is not in the mask
+        assert!(!friday_night.contains(at(2, 1, 0)));
+    }
+
+    #[test]
+    fn equal_bounds_cover_the_whole_day() {
+        let weekends = window(&[Weekday::Sat, Weekday::Sun], "00:00", "00:00");
+        assert!(weekends.contains(at(3, 12, 0)));
+        assert!(!weekends.contains(at(2, 12, 0)));
+    }
+
+    #[test]
+    fn disabled_or_empty_schedule_never_blocks() {
+        let disabled = Schedule::default();
+        assert!(disabled.allows(at(2, 12, 0)));
+        assert_eq!(disabled.until_next_boundary(Local::now()), None);
+
+        let empty = schedule(Vec::new());
+        assert!(empty.validate().is_err());
+        assert!(!empty.allows(at(2, 12, 0)));
+        assert_eq!(empty.until_next_boundary(Local::now()), None);
+    }
+
+    #[test]
+    fn allows_only_inside_a_window() {
+        let s = schedule(vec![window(&[], "09:00", "17:00"), window(&[], "23:00", "02:00")]);
+        assert!(s.allows(at(2, 10, 0)));
+        assert!(s.allows(at(3, 1, 0)));
+        assert!(!s.allows(at(2, 18, 0)));
+    }
+
+    #[test]
+    fn next_boundary_skips_one_exactly_at_now() {
+        let s = schedule(vec![window(&[], "23:00", "23:30")]);
+        let now = Local.from_local_datetime(&at(2, 23, 0)).unwrap();
+        assert_eq!(s.until_next_boundary(now), Some(Duration::from_secs(30 * 60)));
+    }
+
+    #[test]
+    fn next_boundary_crosses_midnight() {
+        let s = schedule(vec![window(&[], "23:00", "00:30")]);
+        let now = Local.from_local_datetime(&at(2, 23, 45)).unwrap();
+        assert_eq!(s.until_next_boundary(now), Some(Duration::from_secs(45 * 60)));
+    }
+
+    #[test]
+    fn next_boundary_is_capped_at_an_hour() {
+        let s = schedule(vec![window(&[], "09:00", "17:00")]);
+        let now = Local.from_local_datetime(&at(2, 18, 0)).unwrap();
+        assert_eq!(s.until_next_boundary(now), Some(Duration::from_secs(3600)));
+    }
+}
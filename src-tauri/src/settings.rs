@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::archive::ArchivePolicy;
+use crate::conflict::ConflictPolicy;
+use crate::download::SYSTEM_GUARDRAILS;
+use crate::retry::RetryPolicy;
+use crate::routing::RoutingRule;
+use crate::schedule::Schedule;
+
+/// User-adjustable settings that survive restarts. Every field has a default
+/// so files written by older builds keep loading.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct AppSettings {
+    pub max_concurrent: usize,
+    pub host_limits: HostLimits,
+    pub bandwidth: BandwidthLimits,
+    pub schedule: Schedule,
+    pub pause_strategy: PauseStrategy,
+    pub disk_space: DiskSpace,
+    /// Default file name template; `None` keeps yt-dlp's own naming.
+    pub output_template: Option<String>,
+    /// Evaluated in order when a download is enqueued without a folder of its
+    /// own; the first match picks the folder.
+    pub routing_rules: Vec<RoutingRule>,
+    /// Default for downloads whose target file already exists.
+    pub conflict_policy: ConflictPolicy,
+    /// What to do with videos already in the download archive.
+    pub archive_policy: ArchivePolicy,
+    /// Automatic retries of failed downloads.
+    pub retry_policy: RetryPolicy,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            max_concurrent: SYSTEM_GUARDRAILS.max_concurrent_downloads,
+            host_limits: HostLimits::default(),
+            bandwidth: BandwidthLimits::default(),
+            schedule: Schedule::default(),
+            pause_strategy: PauseStrategy::default(),
+            disk_space: DiskSpace::default(),
+            output_template: None,
+            routing_rules: Vec::new(),
+            conflict_policy: ConflictPolicy::default(),
+            archive_policy: ArchivePolicy::default(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+}
+
+pub fn validate_max_concurrent(max: usize) -> Result<(), String> {
+    if max == 0 || max > SYSTEM_GUARDRAILS.max_concurrent_limit {
+        return Err(format!(
+            "max_concurrent must be between 1 and {}",
+            SYSTEM_GUARDRAILS.max_concurrent_limit
+        ));
+    }
+    Ok(())
+}
+
+/// Per-site caps applied on top of the global limit. Hosts are compared
+/// without a leading `www.`; an override for `youtube.com` also covers
+/// `m.youtube.com` and counts both against the same budget.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct HostLimits {
+    /// Cap for any host without an override. `None` leaves only the global limit.
+    pub per_host: Option<usize>,
+    pub overrides: HashMap<String, usize>,
+}
+
+impl HostLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        let limits = self.per_host.iter().chain(self.overrides.values());
+        for &limit in limits {
+            validate_max_concurrent(limit)?;
+        }
+        if self.overrides.keys().any(|host| host.trim().is_empty()) {
+            return Err("Host overrides must name a host".to_string());
+        }
+        Ok(())
+    }
+
+    /// The key tasks for `host` are counted under: the matching override, or
+    /// the host itself.
+    pub fn bucket(&self, host: &str) -> String {
+        self.overrides
+            .keys()
+            .filter(|key| host == key.as_str() || host.ends_with(&format!(".{}", key)))
+            .max_by_key(|key| key.len())
+            .cloned()
+            .unwrap_or_else(|| host.to_string())
+    }
+
+    pub fn limit_for(&self, bucket: &str) -> Option<usize> {
+        self.overrides.get(bucket).copied().or(self.per_host)
+    }
+}
+
+/// Download rate caps in bytes per second. `None` means unlimited.
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct BandwidthLimits {
+    /// Shared equally by all running downloads.
+    pub global: Option<u64>,
+    /// Upper bound for any single download.
+    pub per_task: Option<u64>,
+}
+
+impl BandwidthLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        let min = SYSTEM_GUARDRAILS.min_rate_limit;
+        if self.global.iter().chain(self.per_task.iter()).any(|&rate| rate < min) {
+            return Err(format!("Rate limits must be at least {} bytes/s", min));
+        }
+        Ok(())
+    }
+}
+
+/// How `pause_download` stops a running download.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PauseStrategy {
+    /// SIGSTOP/SIGCONT the process. Unix only; long pauses can let the
+    /// server drop the connection.
+    Suspend,
+    /// Kill the process, keep the partial files and relaunch with `--continue`.
+    /// Works everywhere and survives app restarts.
+    Restart,
+}
+
+impl Default for PauseStrategy {
+    fn default() -> Self {
+        if cfg!(unix) {
+            PauseStrategy::Suspend
+        } else {
+            PauseStrategy::Restart
+        }
+    }
+}
+
+impl PauseStrategy {
+    pub fn validate(self) -> Result<(), String> {
+        if self == PauseStrategy::Suspend && !cfg!(unix) {
+            return Err("Suspending downloads is not supported on this platform".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Free-space guards for download folders, in bytes.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct DiskSpace {
+    /// Room that must remain after a task's estimated size before it may start.
+    pub safety_margin: u64,
+    /// Running downloads are paused when their folder drops below this.
+    pub min_free: u64,
+}
+
+impl Default for DiskSpace {
+    fn default() -> Self {
+        Self {
+            safety_margin: 1024 * 1024 * 1024,
+            min_free: 512 * 1024 * 1024,
+        }
+    }
+}
+
+impl DiskSpace {
+    pub fn validate(&self) -> Result<(), String> {
+        // Otherwise a task could start only to be paused straight away
+        if self.safety_margin < self.min_free {
+            return Err("The safety margin must be at least the pause threshold".to_string());
+        }
+        Ok(())
+    }
+}
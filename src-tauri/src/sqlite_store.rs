@@ -0,0 +1,461 @@
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row, Transaction};
+use std::path::Path;
+use std::sync::Mutex;
+use crate::download::{now_millis, url_host, DownloadStatus, Priority};
+use crate::persistence::{HistoryPage, HistoryQuery, HistoryRecord, PersistedTask, TaskStore};
+
+/// Schema migrations, applied in order. The database's `user_version` records
+/// how many have run, so entries must never be edited or reordered.
+const MIGRATIONS: &[Migration] = &[
+    // 1: initial task table
+    Migration::Sql("CREATE TABLE tasks (
+        id TEXT PRIMARY KEY NOT NULL,
+        url TEXT NOT NULL,
+        title TEXT NOT NULL,
+        status TEXT NOT NULL,
+        progress REAL NOT NULL DEFAULT 0,
+        download_dir TEXT,
+        options TEXT,
+        retry_count INTEGER NOT NULL DEFAULT 0,
+        error TEXT,
+        created_seq INTEGER NOT NULL
+    );
+    CREATE INDEX idx_tasks_status ON tasks(status);"),
+    // 2: history details
+    Migration::Sql("ALTER TABLE tasks ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE tasks ADD COLUMN started_at INTEGER;
+    ALTER TABLE tasks ADD COLUMN finished_at INTEGER;
+    ALTER TABLE tasks ADD COLUMN file_size INTEGER;
+    ALTER TABLE tasks ADD COLUMN avg_speed INTEGER;
+    ALTER TABLE tasks ADD COLUMN format_spec TEXT;
+    ALTER TABLE tasks ADD COLUMN extractor TEXT;
+    ALTER TABLE tasks ADD COLUMN host TEXT;
+    ALTER TABLE tasks ADD COLUMN error_code TEXT;
+    CREATE INDEX idx_tasks_history ON tasks(COALESCE(finished_at, created_at));"),
+    // 3: queue ordering
+    Migration::Sql("ALTER TABLE tasks ADD COLUMN priority TEXT NOT NULL DEFAULT 'normal';
+    ALTER TABLE tasks ADD COLUMN queue_position INTEGER NOT NULL DEFAULT 0;"),
+    // 4: cleared from the download list
+    Migration::Sql("ALTER TABLE tasks ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;"),
+    // 5: history details for rows written before 2
+    Migration::Data(backfill_history),
+];
+
+enum Migration {
+    Sql(&'static str),
+    /// A data fix that needs more than SQL, e.g. parsing URLs.
+    Data(fn(&Transaction) -> rusqlite::Result<()>),
+}
+
+/// Migration 2 left older rows without a host and with a `created_at` of 0.
+/// The host comes from the URL; the creation time from the task's other
+/// timestamps, or the time of the upgrade when it has none.
+fn backfill_history(tx: &Transaction) -> rusqlite::Result<()> {
+    let missing: Vec<(String, String)> = tx
+        .prepare("SELECT id, url FROM tasks WHERE host IS NULL")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    let mut set_host = tx.prepare("UPDATE tasks SET host = ?2 WHERE id = ?1")?;
+    for (id, url) in missing {
+        if let Some(host) = url_host(&url) {
+            set_host.execute(params![id, host])?;
+        }
+    }
+    tx.execute(
+        "UPDATE tasks SET created_at = COALESCE(started_at, finished_at, ?1) WHERE created_at = 0",
+        params![now_millis() as i64],
+    )?;
+    Ok(())
+}
+
+const TASK_COLUMNS: &str = "id, url, title, status, progress, download_dir, options, retry_count, error,
+    created_at, started_at, finished_at, file_size, avg_speed, extractor, priority, queue_position, archived";
+
+pub struct SqliteTaskStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteTaskStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn = Connection::open(path).map_err(|e| e.to_string())?;
+        conn.pragma_update(None, "journal_mode", "WAL").map_err(|e| e.to_string())?;
+        let store = Self { conn: Mutex::new(conn) };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    fn migrate(&self) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let current: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(current) {
+            let version = index + 1;
+            let tx = conn.transaction().map_err(|e| e.to_string())?;
+            match migration {
+                Migration::Sql(sql) => tx.execute_batch(sql),
+                Migration::Data(backfill) => backfill(&tx),
+            }
+            .map_err(|e| format!("Migration {} failed: {}", version, e))?;
+            tx.pragma_update(None, "user_version", version).map_err(|e| e.to_string())?;
+            tx.commit().map_err(|e| e.to_string())?;
+            log::info!("[PERSISTENCE] Applied database migration {}", version);
+        }
+        Ok(())
+    }
+}
+
+fn status_to_sql(status: &DownloadStatus) -> Result<String, String> {
+    serde_json::to_value(status)
+        .map_err(|e| e.to_string())?
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "Status did not serialize to a string".to_string())
+}
+
+fn status_from_sql(value: &str) -> Result<DownloadStatus, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string())).map_err(|e| e.to_string())
+}
+
+fn priority_to_sql(priority: Priority) -> &'static str {
+    match priority {
+        Priority::Low => "low",
+        Priority::Normal => "normal",
+        Priority::High => "high",
+    }
+}
+
+fn priority_from_sql(value: &str) -> Priority {
+    match value {
+        "low" => Priority::Low,
+        "high" => Priority::High,
+        _ => Priority::Normal,
+    }
+}
+
+fn to_json<T: serde::Serialize>(value: &Option<T>) -> Result<Option<String>, String> {
+    value.as_ref().map(|v| serde_json::to_string(v).map_err(|e| e.to_string())).transpose()
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(value: Option<String>) -> Option<T> {
+    value.and_then(|v| serde_json::from_str(&v).ok())
+}
+
+/// Reads a row selected with `TASK_COLUMNS`. Rows with a status this build
+/// does not know are skipped rather than failing the whole load.
+fn read_task(row: &Row) -> rusqlite::Result<Option<PersistedTask>> {
+    let id: String = row.get(0)?;
+    let status = match status_from_sql(&row.get::<_, String>(3)?) {
+        Ok(s) => s,
+        Err(e) => {
+            log::warn!("[PERSISTENCE] Skipping task {} with unknown status: {}", id, e);
+            return Ok(None);
+        }
+    };
+    Ok(Some(PersistedTask {
+        id,
+        url: row.get(1)?,
+        title: row.get(2)?,
+        status,
+        progress: row.get(4)?,
+        download_dir: row.get(5)?,
+        options: from_json(row.get(6)?),
+        retry_count: row.get(7)?,
+        error: from_json(row.get(8)?),
+        created_at: row.get::<_, i64>(9)? as u64,
+        started_at: row.get::<_, Option<i64>>(10)?.map(|v| v as u64),
+        finished_at: row.get::<_, Option<i64>>(11)?.map(|v| v as u64),
+        file_size: row.get::<_, Option<i64>>(12)?.map(|v| v as u64),
+        avg_speed: row.get::<_, Option<i64>>(13)?.map(|v| v as u64),
+        extractor: row.get(14)?,
+        priority: priority_from_sql(&row.get::<_, String>(15)?),
+        queue_position: row.get(16)?,
+        archived: row.get(17)?,
+    }))
+}
+
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+impl TaskStore for SqliteTaskStore {
+    fn load_tasks(&self) -> Result<Vec<PersistedTask>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM tasks ORDER BY created_seq", TASK_COLUMNS))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([], read_task).map_err(|e| e.to_string())?;
+
+        let mut tasks = Vec::new();
+        for row in rows {
+            if let Some(task) = row.map_err(|e| e.to_string())? {
+                tasks.push(task);
+            }
+        }
+        Ok(tasks)
+    }
+
+    fn upsert_tasks(&self, tasks: &[PersistedTask]) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        {
+            let next_seq: i64 = tx
+                .query_row("SELECT MAX(created_seq) FROM tasks", [], |row| row.get::<_, Option<i64>>(0))
+                .optional()
+                .map_err(|e| e.to_string())?
+                .flatten()
+                .unwrap_or(0)
+                + 1;
+
+            // created_seq is only assigned on insert, so updates keep a task's original position
+            let mut stmt = tx
+                .prepare(
+                    "INSERT INTO tasks (id, url, title, status, progress, download_dir, options, retry_count, error, created_seq,
+                        created_at, started_at, finished_at, file_size, avg_speed, format_spec, extractor, host, error_code,
+                        priority, queue_position, archived)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)
+                     ON CONFLICT(id) DO UPDATE SET
+                        url = excluded.url,
+                        title = excluded.title,
+                        status = excluded.status,
+                        progress = excluded.progress,
+                        download_dir = excluded.download_dir,
+                        options = excluded.options,
+                        retry_count = excluded.retry_count,
+                        error = excluded.error,
+                        created_at = excluded.created_at,
+                        started_at = excluded.started_at,
+                        finished_at = excluded.finished_at,
+                        file_size = excluded.file_size,
+                        avg_speed = excluded.avg_speed,
+                        format_spec = excluded.format_spec,
+                        extractor = excluded.extractor,
+                        host = excluded.host,
+                        error_code = excluded.error_code,
+                        priority = excluded.priority,
+                        queue_position = excluded.queue_position,
+                        archived = excluded.archived",
+                )
+                .map_err(|e| e.to_string())?;
+
+            for (offset, task) in tasks.iter().enumerate() {
+                stmt.execute(params![
+                    task.id,
+                    task.url,
+                    task.title,
+                    status_to_sql(&task.status)?,
+                    task.progress,
+                    task.download_dir,
+                    to_json(&task.options)?,
+                    task.retry_count,
+                    to_json(&task.error)?,
+                    next_seq + offset as i64,
+                    task.created_at as i64,
+                    task.started_at.map(|v| v as i64),
+                    task.finished_at.map(|v| v as i64),
+                    task.file_size.map(|v| v as i64),
+                    task.avg_speed.map(|v| v as i64),
+                    task.options.as_ref().and_then(|o| o.format_spec.clone()),
+                    task.extractor,
+                    url_host(&task.url),
+                    task.error.as_ref().map(|e| e.code()),
+                    priority_to_sql(task.priority),
+                    task.queue_position,
+                    task.archived,
+                ])
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    fn delete_tasks(&self, ids: &[String]) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        {
+            let mut stmt = tx.prepare("DELETE FROM tasks WHERE id = ?1").map_err(|e| e.to_string())?;
+            for id in ids {
+                stmt.execute(params![id]).map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())
+    }
+
+    fn load_tasks_by_id(&self, ids: &[String]) -> Result<Vec<PersistedTask>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut tasks = Vec::new();
+        // Stays well below SQLite's limit on bound parameters
+        for chunk in ids.chunks(500) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let mut stmt = conn
+                .prepare(&format!("SELECT {} FROM tasks WHERE id IN ({}) ORDER BY created_seq", TASK_COLUMNS, placeholders))
+                .map_err(|e| e.to_string())?;
+            let rows = stmt.query_map(params_from_iter(chunk.iter()), read_task).map_err(|e| e.to_string())?;
+            for row in rows {
+                tasks.extend(row.map_err(|e| e.to_string())?);
+            }
+        }
+        Ok(tasks)
+    }
+
+    fn query_history(&self, query: &HistoryQuery) -> Result<HistoryPage, String> {
+        let mut clauses: Vec<String> = Vec::new();
+        let mut args: Vec<Value> = Vec::new();
+
+        if !query.statuses.is_empty() {
+            let placeholders = vec!["?"; query.statuses.len()].join(", ");
+            clauses.push(format!("status IN ({})", placeholders));
+            for status in &query.statuses {
+                args.push(Value::Text(status_to_sql(status)?));
+            }
+        }
+        if let Some(site) = &query.site {
+            clauses.push("(extractor = ? COLLATE NOCASE OR host LIKE ? ESCAPE '\\')".to_string());
+            args.push(Value::Text(site.clone()));
+            args.push(Value::Text(format!("%{}%", escape_like(&site.to_lowercase()))));
+        }
+        if let Some(from) = query.from {
+            clauses.push("COALESCE(finished_at, created_at) >= ?".to_string());
+            args.push(Value::Integer(from as i64));
+        }
+        if let Some(to) = query.to {
+            clauses.push("COALESCE(finished_at, created_at) <= ?".to_string());
+            args.push(Value::Integer(to as i64));
+        }
+        if let Some(search) = &query.search {
+            // LIKE is case-insensitive for ASCII in SQLite
+            clauses.push("(title LIKE ? ESCAPE '\\' OR url LIKE ? ESCAPE '\\')".to_string());
+            let pattern = format!("%{}%", escape_like(search));
+            args.push(Value::Text(pattern.clone()));
+            args.push(Value::Text(pattern));
+        }
+
+        let where_sql = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+
+        let conn = self.conn.lock().unwrap();
+        let total: i64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM tasks {}", where_sql), params_from_iter(args.iter()), |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+
+        let mut page_args = args;
+        page_args.push(Value::Integer(query.effective_limit() as i64));
+        page_args.push(Value::Integer(query.offset as i64));
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM tasks {} ORDER BY created_at DESC, created_seq DESC LIMIT ? OFFSET ?",
+                TASK_COLUMNS, where_sql
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt.query_map(params_from_iter(page_args.iter()), read_task).map_err(|e| e.to_string())?;
+
+        let mut records = Vec::new();
+        for row in rows {
+            if let Some(task) = row.map_err(|e| e.to_string())? {
+                records.push(HistoryRecord::from(task));
+            }
+        }
+        Ok(HistoryPage { total: total as u64, records })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_db(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("vidflow-{}-{}.db", name, uuid::Uuid::new_v4()))
+    }
+
+    /// A database as the first release wrote it.
+    fn create_v1(path: &Path) {
+        let conn = Connection::open(path).unwrap();
+        let Migration::Sql(initial) = MIGRATIONS[0] else { unreachable!() };
+        conn.execute_batch(initial).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        let rows = [
+            ("a", "https://www.youtube.com/watch?v=abc", 1),
+            ("b", "https://vimeo.com/123", 2),
+            ("c", "not a url", 3),
+        ];
+        for (id, url, seq) in rows {
+            conn.execute(
+                "INSERT INTO tasks (id, url, title, status, progress, created_seq) VALUES (?1, ?2, ?1, 'completed', 100, ?3)",
+                params![id, url, seq],
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn migrating_v1_backfills_history_columns() {
+        let path = temp_db("v1");
+        create_v1(&path);
+        let before = now_millis();
+
+        let store = SqliteTaskStore::open(&path).unwrap();
+        let conn = store.conn.lock().unwrap();
+        let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        let rows: Vec<(String, Option<String>, i64)> = conn
+            .prepare("SELECT id, host, created_at FROM tasks ORDER BY created_seq")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        let hosts: Vec<_> = rows.iter().map(|(id, host, _)| (id.as_str(), host.as_deref())).collect();
+        assert_eq!(hosts, [("a", Some("youtube.com")), ("b", Some("vimeo.com")), ("c", None)]);
+        assert!(rows.iter().all(|(_, _, created_at)| *created_at as u64 >= before));
+        drop(conn);
+
+        // Migrated rows still load
+        assert_eq!(store.load_tasks().unwrap().len(), 3);
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn load_tasks_by_id_returns_only_stored_ids() {
+        let path = temp_db("by-id");
+        create_v1(&path);
+        let store = SqliteTaskStore::open(&path).unwrap();
+        let ids = ["c", "a", "missing"].map(String::from);
+        let found: Vec<_> = store.load_tasks_by_id(&ids).unwrap().into_iter().map(|t| t.id).collect();
+        assert_eq!(found, ["a", "c"]);
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn backfill_keeps_existing_values() {
+        let path = temp_db("current");
+        let store = SqliteTaskStore::open(&path).unwrap();
+        {
+            let conn = store.conn.lock().unwrap();
+            conn.execute(
+                "INSERT INTO tasks (id, url, title, status, created_seq, created_at, started_at, host)
+                 VALUES ('a', 'https://example.com/v', 'a', 'queued', 1, 0, 42, 'custom.host')",
+                [],
+            )
+            .unwrap();
+        }
+        let mut conn = store.conn.lock().unwrap();
+        let tx = conn.transaction().unwrap();
+        backfill_history(&tx).unwrap();
+        let (host, created_at): (String, i64) = tx
+            .query_row("SELECT host, created_at FROM tasks WHERE id = 'a'", [], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!((host.as_str(), created_at), ("custom.host", 42));
+        drop(tx);
+        drop(conn);
+        drop(store);
+        let _ = std::fs::remove_file(&path);
+    }
+}
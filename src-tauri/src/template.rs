@@ -0,0 +1,344 @@
+use crate::download::{VideoMetadata, SYSTEM_GUARDRAILS};
+
+/// yt-dlp's own default, `%(title)s [%(id)s].%(ext)s`, in template syntax.
+pub const DEFAULT_TEMPLATE: &str = "{title} [{id}].{ext}";
+
+/// Fields a template may use. Each maps onto the yt-dlp output field of the same name.
+const FIELDS: &[&str] = &[
+    "title", "id", "ext", "uploader", "channel", "upload_date", "extractor",
+    "playlist", "playlist_index", "resolution", "height", "format_id",
+];
+
+/// What yt-dlp substitutes for a field the video does not have.
+const MISSING: &str = "NA";
+
+/// Names Windows reserves regardless of extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Text(String),
+    Field(&'static str),
+}
+
+/// A file name template such as `{uploader}/{upload_date} - {title} [{id}].{ext}`.
+/// `/` separates folders below the download directory and `{{`/`}}` are
+/// literal braces. Only whitelisted fields are accepted and the literal text
+/// must be valid in a Windows path, so a template that passes `parse` works
+/// on every platform.
+#[derive(Debug, Clone)]
+pub struct OutputTemplate {
+    segments: Vec<Segment>,
+}
+
+impl OutputTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.trim().chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("Unclosed '{{{}' in template", name)),
+                        }
+                    }
+                    let field = FIELDS
+                        .iter()
+                        .find(|f| **f == name.trim())
+                        .ok_or_else(|| format!("Unknown template field '{{{}}}', expected one of: {}", name, FIELDS.join(", ")))?;
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Field(field));
+                }
+                '}' => return Err("Unmatched '}' in template; write '}}' for a literal brace".to_string()),
+                '\\' => text.push('/'),
+                c if c.is_control() || "<>:\"|?*%".contains(c) => {
+                    return Err(format!("Character '{}' is not allowed in a file name template", c.escape_default()));
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        let parsed = Self { segments };
+        parsed.check_components()?;
+        Ok(parsed)
+    }
+
+    /// Rejects templates that would escape the download directory or produce
+    /// an empty folder or file name.
+    fn check_components(&self) -> Result<(), String> {
+        if self.segments.is_empty() {
+            return Err("Template is empty".to_string());
+        }
+        if matches!(self.segments.first(), Some(Segment::Text(t)) if t.starts_with('/')) {
+            return Err("Template must be relative to the download directory".to_string());
+        }
+        // Fields never contain a separator once sanitised, so the shape is decided by the text alone
+        let shape: String = self
+            .segments
+            .iter()
+            .map(|s| match s {
+                Segment::Text(t) => t.as_str(),
+                Segment::Field(_) => "x",
+            })
+            .collect();
+        for component in shape.split('/') {
+            match component.trim() {
+                "" => return Err("Template contains an empty folder name".to_string()),
+                "." | ".." => return Err("Template must not contain '.' or '..' folders".to_string()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn has_field(&self, name: &str) -> bool {
+        self.segments.iter().any(|s| matches!(s, Segment::Field(f) if *f == name))
+    }
+
+    /// The template in yt-dlp's `-o` syntax. An extension is appended when the
+    /// template has none, since yt-dlp needs it to tell formats apart.
+    pub fn to_ytdlp(&self) -> String {
+        let mut out: String = self
+            .segments
+            .iter()
+            .map(|s| match s {
+                Segment::Text(t) => t.clone(),
+                Segment::Field(f) => format!("%({})s", f),
+            })
+            .collect();
+        if !self.has_field("ext") {
+            out.push_str(".%(ext)s");
+        }
+        out
+    }
+
+    /// The relative path yt-dlp would produce for `metadata`, sanitised the
+    /// way `--windows-filenames --trim-filenames` does. `ext` is the file
+    /// extension the chosen format ends up with.
+    pub fn render(&self, metadata: &VideoMetadata, format_id: Option<&str>, ext: &str) -> String {
+        let format = format_id.and_then(|id| metadata.formats.iter().find(|f| f.format_id == id));
+        let value = |field: &str| -> Option<String> {
+            match field {
+                "title" => Some(metadata.title.clone()),
+                "id" => Some(metadata.id.clone()),
+                "ext" => Some(ext.to_string()),
+                "uploader" => metadata.uploader.clone(),
+                "channel" => metadata.channel.clone(),
+                "upload_date" => metadata.upload_date.clone(),
+                "extractor" => metadata.extractor.clone(),
+                "playlist" => metadata.is_playlist.then(|| metadata.title.clone()),
+                "resolution" => format.and_then(|f| f.resolution.clone()),
+                "height" => format.and_then(|f| f.height).map(|h| h.to_string()),
+                "format_id" => format_id.map(str::to_string),
+                _ => None,
+            }
+        };
+
+        let mut path: String = self
+            .segments
+            .iter()
+            .map(|s| match s {
+                Segment::Text(t) => t.clone(),
+                Segment::Field(f) => sanitize_value(&value(f).unwrap_or_else(|| MISSING.to_string())),
+            })
+            .collect();
+        if !self.has_field("ext") {
+            path.push('.');
+            path.push_str(ext);
+        }
+
+        let components: Vec<&str> = path.split('/').collect();
+        let last = components.len() - 1;
+        components
+            .iter()
+            .enumerate()
+            .map(|(i, c)| sanitize_component(c, i == last))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Replaces characters Windows does not allow in names with their full-width
+/// look-alikes, as yt-dlp does, and drops control characters.
+fn sanitize_value(value: &str) -> String {
+    value
+        .chars()
+        .filter_map(|c| match c {
+            '/' => Some('\u{29F8}'),
+            '\\' => Some('\u{29F9}'),
+            '"' | '*' | ':' | '<' | '>' | '?' | '|' => char::from_u32(c as u32 + 0xFEE0),
+            '\n' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+/// Trims what Windows strips from names, renames reserved device names and
+/// shortens the name (the file name without its extension) to the length limit.
+fn sanitize_component(component: &str, is_file: bool) -> String {
+    let max = SYSTEM_GUARDRAILS.max_filename_len;
+    let (stem, ext) = match component.rsplit_once('.') {
+        // An empty stem (e.g. an empty title) is filled in below rather than leaving a dotfile
+        Some((stem, ext)) if is_file => (stem, Some(ext)),
+        _ => (component, None),
+    };
+    let mut stem: String = stem.chars().take(max).collect();
+    stem = stem.trim_end_matches([' ', '.']).to_string();
+    if stem.is_empty() {
+        stem.push('_');
+    }
+    let base = stem.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(base)) {
+        stem.insert(0, '_');
+    }
+    match ext {
+        Some(ext) => format!("{}.{}", stem, ext),
+        None => stem,
+    }
+}
+
+/// The extension a download ends up with for a `DownloadOptions::format_spec`.
+pub fn output_ext(metadata: &VideoMetadata, format_spec: Option<&str>) -> String {
+    match format_spec {
+        Some("audio") => "mp3".to_string(),
+        // Video formats are merged into mp4
+        Some(_) => "mp4".to_string(),
+        None => metadata.formats.last().map(|f| f.ext.clone()).unwrap_or_else(|| "mp4".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::download::VideoFormat;
+
+    fn metadata(title: &str, uploader: Option<&str>) -> VideoMetadata {
+        VideoMetadata {
+            id: "dQw4w9WgXcQ".to_string(),
+            title: title.to_string(),
+            thumbnail: String::new(),
+            webpage_url: String::new(),
+            duration: None,
+            formats: vec![format("137", "mp4"), format("251", "webm")],
+            is_playlist: false,
+            entries: None,
+            uploader: uploader.map(str::to_string),
+            channel: None,
+            upload_date: Some("20091025".to_string()),
+            extractor: Some("youtube".to_string()),
+            archive_id: None,
+            total_entries: None,
+            has_more: false,
+        }
+    }
+
+    fn format(format_id: &str, ext: &str) -> VideoFormat {
+        VideoFormat {
+            format_id: format_id.to_string(),
+            ext: ext.to_string(),
+            resolution: None,
+            width: None,
+            height: Some(1080),
+            fps: None,
+            filesize: None,
+            vcodec: None,
+            acodec: None,
+            note: None,
+        }
+    }
+
+    fn render(template: &str, metadata: &VideoMetadata) -> String {
+        OutputTemplate::parse(template).unwrap().render(metadata, Some("137"), "mp4")
+    }
+
+    #[test]
+    fn rejects_templates_that_leave_the_download_directory() {
+        for template in ["../{title}", "{uploader}/../../{title}", "..\\{title}", "./{title}", "/etc/{title}", "\\server\\{title}", "C:{title}"] {
+            assert!(OutputTemplate::parse(template).is_err(), "{} should be rejected", template);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        for template in ["", "   ", "{bogus}", "{title", "title}", "a//{title}", "{title}/", "50%{title}", "{title}?"] {
+            assert!(OutputTemplate::parse(template).is_err(), "{:?} should be rejected", template);
+        }
+    }
+
+    #[test]
+    fn field_values_cannot_inject_folders() {
+        let md = metadata("../../etc/passwd", Some(".."));
+        let path = render("{uploader}/{title}.{ext}", &md);
+        assert_eq!(path, "_/..\u{29F8}..\u{29F8}etc\u{29F8}passwd.mp4");
+        assert_eq!(path.split('/').count(), 2);
+    }
+
+    #[test]
+    fn replaces_characters_windows_rejects() {
+        let md = metadata("Rick: \"Never\" <Gonna>?", None);
+        assert_eq!(render("{title}.{ext}", &md), "Rick\u{FF1A} \u{FF02}Never\u{FF02} \u{FF1C}Gonna\u{FF1E}\u{FF1F}.mp4");
+    }
+
+    #[test]
+    fn renames_reserved_windows_names() {
+        assert_eq!(render("{uploader}/{title}.{ext}", &metadata("nul", Some("CON"))), "_CON/_nul.mp4");
+        assert_eq!(render("{title}.{ext}", &metadata("Com1.part two", None)), "_Com1.part two.mp4");
+        assert_eq!(render("{title}.{ext}", &metadata("Console", None)), "Console.mp4");
+    }
+
+    #[test]
+    fn fills_in_missing_and_empty_fields() {
+        assert_eq!(render("{uploader}/{title}.{ext}", &metadata("Video", None)), "NA/Video.mp4");
+        assert_eq!(render("{uploader}/{title}.{ext}", &metadata("Video", Some(""))), "_/Video.mp4");
+        assert_eq!(render("{title}.{ext}", &metadata("", None)), "_.mp4");
+        assert_eq!(render("{title}.{ext}", &metadata(" ... ", None)), "_.mp4");
+    }
+
+    #[test]
+    fn trims_long_names_but_keeps_the_extension() {
+        let md = metadata(&"a".repeat(500), None);
+        let path = render("{title} [{id}].{ext}", &md);
+        assert_eq!(path.len(), SYSTEM_GUARDRAILS.max_filename_len + ".mp4".len());
+        assert!(path.ends_with("a.mp4"));
+    }
+
+    #[test]
+    fn appends_the_extension_when_missing() {
+        let template = OutputTemplate::parse("{uploader}/{title}").unwrap();
+        assert_eq!(template.to_ytdlp(), "%(uploader)s/%(title)s.%(ext)s");
+        assert_eq!(template.render(&metadata("Video", Some("Rick")), None, "mp3"), "Rick/Video.mp3");
+        assert_eq!(OutputTemplate::parse(DEFAULT_TEMPLATE).unwrap().to_ytdlp(), "%(title)s [%(id)s].%(ext)s");
+    }
+
+    #[test]
+    fn output_ext_follows_the_format_choice() {
+        let md = metadata("Video", None);
+        assert_eq!(output_ext(&md, Some("audio")), "mp3");
+        assert_eq!(output_ext(&md, Some("137")), "mp4");
+        assert_eq!(output_ext(&md, None), "webm");
+        let no_formats = VideoMetadata { formats: Vec::new(), ..md };
+        assert_eq!(output_ext(&no_formats, None), "mp4");
+    }
+}
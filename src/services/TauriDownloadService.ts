@@ -1,27 +1,209 @@
 import { invoke } from "@tauri-apps/api/core";
-import { IDownloadService, Download, VideoMetadata } from "@/types/download";
+import { IDownloadService, Download, VideoMetadata, RetryPolicy, HistoryQuery, HistoryPage, AppSettings, HostLimits, Priority, BandwidthLimits, Schedule, PauseStrategy, DiskSpace, RoutingRule, RouteContext, ConflictPolicy, ArchivePolicy, ArchiveImport, RouteDecision, DownloadStatus, BulkResult, SweepReport, PlaylistRequest, PlaylistEnqueued, PlaylistProgressPayload, PlaylistPage } from "@/types/download";
 
 export class TauriDownloadService implements IDownloadService {
     async getVideoMetadata(url: string): Promise<VideoMetadata> {
         return await invoke<VideoMetadata>("get_video_metadata", { url });
     }
 
-    async startDownload(url: string, options: { title: string, path?: string | null, format?: string | null, cookies?: string | null }): Promise<string> {
+    async startDownload(url: string, options: { title: string, path?: string | null, format?: string | null, cookies?: string | null, extraArgs?: string[], priority?: Priority, rateLimit?: number | null, ignoreSchedule?: boolean, estimatedSize?: number | null, outputTemplate?: string | null, conflictPolicy?: ConflictPolicy | null, archiveId?: string | null, ignoreArchive?: boolean, route?: RouteContext }): Promise<string> {
         return await invoke<string>("start_download", {
             url,
             title: options.title,
-            path: options?.path,
-            formatSpec: options?.format,
-            cookies: options?.cookies
+            options: {
+                output_dir: options?.path ?? null,
+                format_spec: options?.format ?? null,
+                cookies: options?.cookies ?? null,
+                extra_args: options?.extraArgs ?? [],
+                rate_limit: options?.rateLimit ?? null,
+                ignore_schedule: options?.ignoreSchedule ?? false,
+                estimated_size: options?.estimatedSize ?? null,
+                output_template: options?.outputTemplate ?? null,
+                conflict_policy: options?.conflictPolicy ?? null,
+                archive_id: options?.archiveId ?? null,
+                ignore_archive: options?.ignoreArchive ?? false
+            },
+            priority: options?.priority ?? null,
+            route: options?.route ?? null
         });
     }
 
+    async getPlaylistPage(url: string, offset: number, limit: number): Promise<PlaylistPage> {
+        return await invoke<PlaylistPage>("get_playlist_page", { url, offset, limit });
+    }
+
+    async streamPlaylist(url: string, offset: number, pageSize?: number): Promise<string> {
+        return await invoke<string>("stream_playlist", { url, offset, pageSize: pageSize ?? null });
+    }
+
+    async cancelPlaylistStream(id: string): Promise<void> {
+        await invoke("cancel_playlist_stream", { id });
+    }
+
+    async enqueuePlaylist(request: PlaylistRequest, options: { path?: string | null, format?: string | null, cookies?: string | null, priority?: Priority }): Promise<PlaylistEnqueued> {
+        return await invoke<PlaylistEnqueued>("enqueue_playlist", {
+            request,
+            options: {
+                output_dir: options?.path ?? null,
+                format_spec: options?.format ?? null,
+                cookies: options?.cookies ?? null,
+                extra_args: []
+            },
+            priority: options?.priority ?? null,
+            route: null
+        });
+    }
+
+    async listPlaylists(): Promise<PlaylistProgressPayload[]> {
+        return await invoke<PlaylistProgressPayload[]>("list_playlists");
+    }
+
+    async cancelPlaylist(id: string): Promise<BulkResult[]> {
+        return await invoke<BulkResult[]>("cancel_playlist", { id });
+    }
+
+    async retryPlaylist(id: string): Promise<BulkResult[]> {
+        return await invoke<BulkResult[]>("retry_playlist", { id });
+    }
+
     async pauseDownload(id: string): Promise<void> {
         await invoke("pause_download", { id });
     }
 
-    async resumeDownload(id: string): Promise<void> {
-        await invoke("resume_download", { id });
+    async resumeDownload(id: string): Promise<DownloadStatus> {
+        return await invoke<DownloadStatus>("resume_download", { id });
+    }
+
+    async retryDownload(id: string): Promise<void> {
+        await invoke("retry_download", { id });
+    }
+
+    async pauseAll(): Promise<BulkResult[]> {
+        return await invoke<BulkResult[]>("pause_all");
+    }
+
+    async resumeAll(): Promise<BulkResult[]> {
+        return await invoke<BulkResult[]>("resume_all");
+    }
+
+    async cancelQueued(): Promise<BulkResult[]> {
+        return await invoke<BulkResult[]>("cancel_queued");
+    }
+
+    async retryFailed(): Promise<BulkResult[]> {
+        return await invoke<BulkResult[]>("retry_failed");
+    }
+
+    async clearCompleted(): Promise<BulkResult[]> {
+        return await invoke<BulkResult[]>("clear_completed");
+    }
+
+    async removeDownload(id: string, deleteFiles: boolean): Promise<void> {
+        await invoke("remove_download", { id, deleteFiles });
+    }
+
+    async clearHistory(filter: HistoryQuery, deleteFiles: boolean): Promise<BulkResult[]> {
+        return await invoke<BulkResult[]>("clear_history", { filter, deleteFiles });
+    }
+
+    async scanOrphanedFiles(): Promise<SweepReport> {
+        return await invoke<SweepReport>("scan_orphaned_files");
+    }
+
+    async deleteOrphanedFiles(paths: string[]): Promise<SweepReport> {
+        return await invoke<SweepReport>("delete_orphaned_files", { paths });
+    }
+
+    async getQueue(): Promise<string[]> {
+        return await invoke<string[]>("get_queue");
+    }
+
+    async setPriority(id: string, priority: Priority): Promise<string[]> {
+        return await invoke<string[]>("set_priority", { id, priority });
+    }
+
+    async reorderDownload(id: string, index: number): Promise<string[]> {
+        return await invoke<string[]>("reorder_download", { id, index });
+    }
+
+    async moveDownloadToTop(id: string): Promise<string[]> {
+        return await invoke<string[]>("move_download_to_top", { id });
+    }
+
+    async moveDownloadToBottom(id: string): Promise<string[]> {
+        return await invoke<string[]>("move_download_to_bottom", { id });
+    }
+
+    async getRetryPolicy(): Promise<RetryPolicy> {
+        return await invoke<RetryPolicy>("get_retry_policy");
+    }
+
+    async setRetryPolicy(policy: RetryPolicy): Promise<void> {
+        await invoke("set_retry_policy", { policy });
+    }
+
+    async getSettings(): Promise<AppSettings> {
+        return await invoke<AppSettings>("get_settings");
+    }
+
+    async setMaxConcurrent(max: number): Promise<void> {
+        await invoke("set_max_concurrent", { max });
+    }
+
+    async setHostLimits(limits: HostLimits): Promise<void> {
+        await invoke("set_host_limits", { limits });
+    }
+
+    async setBandwidthLimits(limits: BandwidthLimits): Promise<void> {
+        await invoke("set_bandwidth_limits", { limits });
+    }
+
+    async setSchedule(schedule: Schedule): Promise<void> {
+        await invoke("set_schedule", { schedule });
+    }
+
+    async setPauseStrategy(strategy: PauseStrategy): Promise<void> {
+        await invoke("set_pause_strategy", { strategy });
+    }
+
+    async setDiskSpace(diskSpace: DiskSpace): Promise<void> {
+        await invoke("set_disk_space", { diskSpace });
+    }
+
+    async setOutputTemplate(template: string | null): Promise<void> {
+        await invoke("set_output_template", { template });
+    }
+
+    async setRoutingRules(rules: RoutingRule[]): Promise<void> {
+        await invoke("set_routing_rules", { rules });
+    }
+
+    async setConflictPolicy(policy: ConflictPolicy): Promise<void> {
+        await invoke("set_conflict_policy", { policy });
+    }
+
+    async setArchivePolicy(policy: ArchivePolicy): Promise<void> {
+        await invoke("set_archive_policy", { policy });
+    }
+
+    async importDownloadArchive(path: string): Promise<ArchiveImport> {
+        return await invoke<ArchiveImport>("import_download_archive", { path });
+    }
+
+    async exportDownloadArchive(path: string): Promise<number> {
+        return await invoke<number>("export_download_archive", { path });
+    }
+
+    async resolveOutputDir(url: string, formatSpec: string | null, route?: RouteContext): Promise<RouteDecision> {
+        return await invoke<RouteDecision>("resolve_output_dir", { url, formatSpec, route: route ?? null });
+    }
+
+    async previewOutputTemplate(template: string | null, metadata: VideoMetadata, formatSpec: string | null): Promise<string> {
+        return await invoke<string>("preview_output_template", { template, metadata, formatSpec });
+    }
+
+    async startDownloadNow(id: string): Promise<void> {
+        await invoke("start_download_now", { id });
     }
 
     async cancelDownload(id: string): Promise<void> {
@@ -32,6 +214,10 @@ export class TauriDownloadService implements IDownloadService {
         return [];
     }
 
+    async queryHistory(query: HistoryQuery): Promise<HistoryPage> {
+        return await invoke<HistoryPage>("query_history", { query });
+    }
+
     async showInFolder(path: string): Promise<void> {
         await invoke('show_in_folder', { path });
     }
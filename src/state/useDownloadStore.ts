@@ -6,13 +6,18 @@ import {
     requestPermission,
     sendNotification
 } from '@tauri-apps/plugin-notification';
-import { Download, IDownloadService, DownloadProgressPayload, VideoMetadata } from "@/types/download";
+import { Download, IDownloadService, DownloadProgressPayload, VideoMetadata, SweepReport, DiskSpacePayload, ArchiveMatchPayload, PlaylistSelection, PlaylistProgressPayload, PlaylistPagePayload } from "@/types/download";
 import { TauriDownloadService } from "@/services/TauriDownloadService";
 import { formatBytes, formatSpeed, formatETA } from "@/utils/formatUtils";
 
 // Use real service
 const api: IDownloadService = new TauriDownloadService();
 
+// A finished stream is already gone on the backend, so failures are expected
+const stopPlaylistStream = (id: string | null) => {
+    if (id) api.cancelPlaylistStream(id).catch(() => { });
+};
+
 interface AnalysisContext {
     url: string;
     metadata: VideoMetadata;
@@ -27,8 +32,16 @@ interface DownloadState {
     // Analysis State
     isAnalyzing: boolean;
     analysisCtx: AnalysisContext | null;
+    // Loads the rest of a large playlist into analysisCtx, page by page
+    playlistStream: string | null;
     prefillUrl: string | null;
 
+    // Leftover fragments found at startup, awaiting confirmation
+    orphanedFiles: SweepReport | null;
+
+    // Playlist groups by id; their entries are ordinary tasks with a groupId
+    playlists: Record<string, PlaylistProgressPayload>;
+
     // Settings
     settings: {
         maxConcurrent: number;
@@ -46,6 +59,7 @@ interface DownloadState {
 
     analyzeUrl: (url: string) => Promise<void>;
     confirmDownload: (formatSpec: string) => Promise<void>;
+    confirmPlaylist: (formatSpec: string, selection?: PlaylistSelection) => Promise<void>;
     processQueue: () => Promise<void>;
     cancelAnalysis: () => void;
 
@@ -53,8 +67,12 @@ interface DownloadState {
     resumeTask: (id: string) => Promise<void>;
     retryTask: (id: string) => Promise<void>;
     cancelTask: (id: string) => Promise<void>;
-    removeTask: (id: string) => Promise<void>;
+    removeTask: (id: string, deleteFiles?: boolean) => Promise<void>;
     openFolder: (id: string) => Promise<void>;
+    cancelPlaylist: (id: string) => Promise<void>;
+    retryPlaylist: (id: string) => Promise<void>;
+    deleteOrphanedFiles: () => Promise<void>;
+    dismissOrphanedFiles: () => void;
 }
 
 export const useDownloadStore = create<DownloadState>()(
@@ -66,7 +84,10 @@ export const useDownloadStore = create<DownloadState>()(
             downloadPath: null,
             isAnalyzing: false,
             analysisCtx: null,
+            playlistStream: null,
             prefillUrl: null,
+            orphanedFiles: null,
+            playlists: {},
             settings: {
                 maxConcurrent: 2,
                 concurrencyMode: true,
@@ -94,6 +115,10 @@ export const useDownloadStore = create<DownloadState>()(
 
                     return { settings: nextSettings };
                 });
+
+                if (newSettings.maxConcurrent !== undefined) {
+                    api.setMaxConcurrent(newSettings.maxConcurrent).catch(e => console.error("Failed to update concurrency limit", e));
+                }
             },
 
             initializeListeners: async () => {
@@ -135,7 +160,9 @@ export const useDownloadStore = create<DownloadState>()(
                                     status: payload.status,
                                     totalSize: formatBytes(payload.total_size),
                                     downloadedBytes: payload.downloaded_bytes ?? newTasks[idx].downloadedBytes,
-                                    filePath: payload.final_path ?? newTasks[idx].filePath
+                                    filePath: payload.final_path ?? newTasks[idx].filePath,
+                                    retryCount: payload.retry_count,
+                                    conflict: payload.conflict ?? newTasks[idx].conflict
                                 };
                                 updated = true;
                             }
@@ -176,6 +203,9 @@ export const useDownloadStore = create<DownloadState>()(
                                 eta: formatETA(payload.eta),
                                 totalSize: formatBytes(payload.total_size),
                                 filePath: payload.final_path,
+                                retryCount: payload.retry_count,
+                                conflict: payload.conflict ?? task.conflict,
+                                errorCode: payload.error_code,
                                 error: payload.error_message || (payload.status === 'error' ? 'Download failed' : undefined)
                             };
                             return { tasks: newTasks };
@@ -189,9 +219,79 @@ export const useDownloadStore = create<DownloadState>()(
                     }
                 });
 
+                // Bulk "clear completed" from the tray or a command
+                await listen<string[]>("downloads-cleared", (event) => {
+                    const cleared = new Set(event.payload);
+                    set(state => ({ tasks: state.tasks.filter(t => !cleared.has(t.id)) }));
+                });
+
                 await listen<string>("binary-error", (event) => {
                     set({ error: event.payload });
                 });
+
+                await listen<DiskSpacePayload>("disk-space-low", (event) => {
+                    const { path, available, required, paused } = event.payload;
+                    set({
+                        error: paused
+                            ? `Downloads paused: only ${formatBytes(available)} left in ${path}`
+                            : `Waiting for space in ${path}: ${formatBytes(required)} needed, ${formatBytes(available)} free`
+                    });
+                });
+
+                await listen<ArchiveMatchPayload>("already-downloaded", (event) => {
+                    set({ error: `"${event.payload.title}" was downloaded before; downloading it again` });
+                });
+
+                await listen<PlaylistProgressPayload>("playlist-progress", (event) => {
+                    set(state => ({ playlists: { ...state.playlists, [event.payload.id]: event.payload } }));
+                });
+
+                await listen<string>("playlist-removed", (event) => {
+                    set(state => {
+                        const { [event.payload]: _removed, ...playlists } = state.playlists;
+                        return { playlists };
+                    });
+                });
+
+                try {
+                    const groups = await api.listPlaylists();
+                    set({ playlists: Object.fromEntries(groups.map(g => [g.id, g])) });
+                } catch (e) {
+                    console.error("Failed to load playlists", e);
+                }
+
+                await listen<PlaylistPagePayload>("playlist-page", (event) => {
+                    const { stream_id, page, error, done } = event.payload;
+                    set(state => {
+                        const current = state.playlistStream === stream_id;
+                        const finished = current && done ? { playlistStream: null } : {};
+                        if (error && current) {
+                            return { ...finished, error: `Could not load the rest of the playlist: ${error}` };
+                        }
+                        // Only pages that continue the analysed playlist are appended
+                        const ctx = state.analysisCtx;
+                        const loaded = ctx?.metadata.entries ?? [];
+                        if (!ctx || !page || page.url !== ctx.url || page.offset !== loaded.length) {
+                            return finished;
+                        }
+                        return {
+                            ...finished,
+                            analysisCtx: {
+                                ...ctx,
+                                metadata: {
+                                    ...ctx.metadata,
+                                    entries: [...loaded, ...page.entries],
+                                    total_entries: page.total ?? ctx.metadata.total_entries,
+                                    has_more: page.has_more
+                                }
+                            }
+                        };
+                    });
+                });
+
+                await listen<SweepReport>("orphaned-files", (event) => {
+                    set({ orphanedFiles: event.payload });
+                });
             },
 
             setDownloadPath: (path: string) => {
@@ -213,13 +313,18 @@ export const useDownloadStore = create<DownloadState>()(
                     return;
                 }
 
-                set({ isAnalyzing: true, error: null });
+                stopPlaylistStream(get().playlistStream);
+                set({ isAnalyzing: true, error: null, playlistStream: null });
                 try {
                     const metadata = await api.getVideoMetadata(url);
                     set({
                         isAnalyzing: false,
                         analysisCtx: { url, metadata }
                     });
+                    if (metadata.is_playlist && metadata.has_more) {
+                        const playlistStream = await api.streamPlaylist(url, metadata.entries?.length ?? 0);
+                        set({ playlistStream });
+                    }
                 } catch (e) {
                     console.error(e);
                     set({ isAnalyzing: false, error: "Failed to fetch video metadata. Check URL or connection." });
@@ -229,15 +334,20 @@ export const useDownloadStore = create<DownloadState>()(
             confirmDownload: async (formatSpec: string) => {
                 const { analysisCtx, downloadPath } = get();
                 if (!analysisCtx) return;
+                if (analysisCtx.metadata.is_playlist && analysisCtx.metadata.entries?.length) {
+                    return get().confirmPlaylist(formatSpec);
+                }
 
                 set({ analysisCtx: null });
 
                 // Principal Hardening: Pre-populate totalSize from metadata if possible
                 let initialSize: string | undefined = undefined;
+                let estimatedSize: number | undefined = undefined;
                 if (formatSpec !== 'audio' && formatSpec !== 'best') {
                     const fmt = analysisCtx.metadata.formats.find(f => f.format_id === formatSpec);
                     if (fmt?.filesize) {
                         initialSize = formatBytes(fmt.filesize);
+                        estimatedSize = fmt.filesize;
                     }
                 }
 
@@ -253,7 +363,14 @@ export const useDownloadStore = create<DownloadState>()(
                     duration: analysisCtx.metadata.duration ?? undefined,
                     progress: 0,
                     status: "queued",
-                    totalSize: initialSize
+                    totalSize: initialSize,
+                    estimatedSize,
+                    archiveId: analysisCtx.metadata.archive_id ?? undefined,
+                    route: {
+                        extractor: analysisCtx.metadata.extractor,
+                        channel: analysisCtx.metadata.channel ?? analysisCtx.metadata.uploader,
+                        playlist: analysisCtx.metadata.is_playlist ? analysisCtx.metadata.id : null
+                    }
                 };
 
                 set((state) => ({
@@ -263,6 +380,54 @@ export const useDownloadStore = create<DownloadState>()(
                 await get().processQueue();
             },
 
+            confirmPlaylist: async (formatSpec: string, selection?: PlaylistSelection) => {
+                const { analysisCtx, downloadPath, settings, playlistStream } = get();
+                if (!analysisCtx) return;
+                const { metadata } = analysisCtx;
+
+                // Enqueues the entries loaded so far
+                stopPlaylistStream(playlistStream);
+                set({ analysisCtx: null, playlistStream: null });
+                try {
+                    // The backend queues the entries itself, so they are added already queued
+                    const result = await api.enqueuePlaylist({
+                        url: analysisCtx.url,
+                        title: metadata.title,
+                        playlist_id: metadata.id,
+                        entries: metadata.entries ?? [],
+                        selection
+                    }, {
+                        path: downloadPath,
+                        format: formatSpec,
+                        cookies: settings.cookies
+                    });
+                    const entries = new Map((metadata.entries ?? []).map(e => [e.id, e]));
+                    const tasks: Download[] = result.children.flatMap(child => {
+                        const entry = entries.get(child.entry_id);
+                        return entry ? [{
+                            id: child.task_id,
+                            url: entry.url,
+                            sourceUrl: entry.url,
+                            title: entry.title,
+                            format: formatSpec === 'audio' ? 'audio' : 'video',
+                            formatSpec,
+                            downloadDir: downloadPath ?? undefined,
+                            duration: entry.duration ?? undefined,
+                            progress: 0,
+                            status: 'queued',
+                            archiveId: entry.archive_id ?? undefined,
+                            groupId: result.group_id
+                        } as Download] : [];
+                    });
+                    set(state => ({ tasks: [...tasks, ...state.tasks] }));
+                    if (result.skipped > 0) {
+                        set({ error: `${result.skipped} playlist entries were skipped as already downloaded` });
+                    }
+                } catch (e) {
+                    set({ error: String(e) });
+                }
+            },
+
             processQueue: async () => {
                 const { tasks, settings } = get();
                 if (!settings.concurrencyMode) return;
@@ -272,7 +437,8 @@ export const useDownloadStore = create<DownloadState>()(
                 ).length;
 
                 if (activeCount < settings.maxConcurrent) {
-                    const nextTask = tasks.find(t => t.status === 'queued');
+                    // Playlist entries are queued by the backend
+                    const nextTask = tasks.find(t => t.status === 'queued' && !t.groupId);
                     if (nextTask) {
                         try {
                             // Move to preparing state
@@ -284,7 +450,10 @@ export const useDownloadStore = create<DownloadState>()(
                                 title: nextTask.title || "Unknown Title",
                                 path: nextTask.downloadDir,
                                 format: nextTask.formatSpec,
-                                cookies: settings.cookies
+                                cookies: settings.cookies,
+                                estimatedSize: nextTask.estimatedSize,
+                                archiveId: nextTask.archiveId,
+                                route: nextTask.route
                             });
 
                             console.log("[DEBUG] Updating task ID from", nextTask.id, "to", realId);
@@ -297,7 +466,8 @@ export const useDownloadStore = create<DownloadState>()(
                         } catch (err) {
                             console.error("Queue start failed:", err);
                             set(state => ({
-                                tasks: state.tasks.map(t => t.id === nextTask.id ? { ...t, status: 'error', error: 'Failed to start' } : t)
+                                // e.g. skipped because the download archive already has it
+                                tasks: state.tasks.map(t => t.id === nextTask.id ? { ...t, status: 'error', error: typeof err === 'string' ? err : 'Failed to start' } : t)
                             }));
                         }
                     }
@@ -305,7 +475,8 @@ export const useDownloadStore = create<DownloadState>()(
             },
 
             cancelAnalysis: () => {
-                set({ analysisCtx: null, isAnalyzing: false, error: null });
+                stopPlaylistStream(get().playlistStream);
+                set({ analysisCtx: null, isAnalyzing: false, error: null, playlistStream: null });
             },
 
             pauseTask: async (id: string) => {
@@ -316,41 +487,26 @@ export const useDownloadStore = create<DownloadState>()(
             },
 
             resumeTask: async (id: string) => {
-                await api.resumeDownload(id);
+                // Either continues in place or re-queues, depending on how it was paused
+                const status = await api.resumeDownload(id);
                 set(state => ({
-                    tasks: state.tasks.map(t => t.id === id ? { ...t, status: 'downloading' } : t)
+                    tasks: state.tasks.map(t => t.id === id ? { ...t, status } : t)
                 }));
             },
 
             retryTask: async (id: string) => {
-                const { tasks, downloadPath } = get();
-                const task = tasks.find(t => t.id === id);
-                if (!task) return;
-
                 try {
-                    set(state => ({ tasks: state.tasks.filter(t => t.id !== id) }));
-
-                    const settings = get().settings;
-                    const newId = await api.startDownload(task.url, {
-                        title: task.title || "Unknown Title",
-                        path: downloadPath,
-                        format: task.formatSpec,
-                        cookies: settings.cookies
-                    });
-
-                    set((state) => ({
-                        tasks: [
-                            {
-                                ...task,
-                                id: newId,
-                                status: 'queued',
-                                progress: 0,
-                                speed: undefined,
-                                eta: undefined,
-                                error: undefined
-                            },
-                            ...state.tasks
-                        ]
+                    // The backend re-queues the same task with its original options
+                    await api.retryDownload(id);
+                    set(state => ({
+                        tasks: state.tasks.map(t => t.id === id ? {
+                            ...t,
+                            status: 'queued',
+                            speed: undefined,
+                            eta: undefined,
+                            error: undefined,
+                            retryCount: (t.retryCount ?? 0) + 1
+                        } : t)
                     }));
                 } catch (e) {
                     console.error("Retry failed", e);
@@ -364,12 +520,18 @@ export const useDownloadStore = create<DownloadState>()(
                 }));
             },
 
-            removeTask: async (id: string) => {
+            removeTask: async (id: string, deleteFiles = false) => {
                 // If active, try to cancel first (ignore errors if already terminal)
                 try {
                     await api.cancelDownload(id);
                 } catch (e) { /* ignore */ }
 
+                try {
+                    await api.removeDownload(id, deleteFiles);
+                } catch (e) {
+                    console.error("Remove failed", e);
+                }
+
                 set(state => ({
                     tasks: state.tasks.filter(t => t.id !== id)
                 }));
@@ -390,7 +552,36 @@ export const useDownloadStore = create<DownloadState>()(
                         await api.showInFolder(downloadPath);
                     }
                 }
-            }
+            },
+
+            cancelPlaylist: async (id: string) => {
+                try {
+                    await api.cancelPlaylist(id);
+                } catch (e) {
+                    set({ error: String(e) });
+                }
+            },
+
+            retryPlaylist: async (id: string) => {
+                try {
+                    await api.retryPlaylist(id);
+                } catch (e) {
+                    set({ error: String(e) });
+                }
+            },
+
+            deleteOrphanedFiles: async () => {
+                const { orphanedFiles } = get();
+                if (!orphanedFiles) return;
+                try {
+                    await api.deleteOrphanedFiles(orphanedFiles.files.map(f => f.path));
+                    set({ orphanedFiles: null });
+                } catch (e) {
+                    set({ error: String(e) });
+                }
+            },
+
+            dismissOrphanedFiles: () => set({ orphanedFiles: null })
         }),
         {
             name: "vidflow-storage",
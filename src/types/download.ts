@@ -8,8 +8,25 @@ export type DownloadStatus =
     | 'error'
     | 'cancelled';
 
+export type DownloadErrorCode =
+    | 'network'
+    | 'http'
+    | 'geo_blocked'
+    | 'login_required'
+    | 'unavailable'
+    | 'unsupported_url'
+    | 'ffmpeg'
+    | 'disk_full'
+    | 'cancelled'
+    | 'verification_failed'
+    | 'spawn'
+    | 'crashed'
+    | 'unknown';
+
 export type DownloadFormat = 'video' | 'audio';
 
+export type Priority = 'low' | 'normal' | 'high';
+
 export interface Download {
     id: string;
     url: string;
@@ -21,13 +38,20 @@ export interface Download {
     eta?: string;
     status: DownloadStatus;
     error?: string;
+    errorCode?: DownloadErrorCode;
     formatSpec?: string;
     downloadDir?: string;
     filePath?: string;
     thumbnail?: string;
     duration?: number;
     totalSize?: string;
+    estimatedSize?: number;  // Bytes, from the chosen format
+    archiveId?: string;
+    groupId?: string;        // Playlist group this entry belongs to
+    route?: RouteContext;
     downloadedBytes?: number;
+    retryCount?: number;
+    conflict?: ConflictOutcome;
 }
 
 // Emitted from Backend to Frontend
@@ -41,10 +65,71 @@ export interface DownloadProgressPayload {
     downloaded_bytes: number | null;
     can_retry?: boolean;
     error_message?: string;
+    error_code?: DownloadErrorCode;
     final_path?: string;
+    retry_count: number;
+    conflict?: ConflictOutcome | null;
     version: number;
 }
 
+// Which entries enqueuePlaylist takes; with neither items nor entry_ids, all of them
+export interface PlaylistSelection {
+    items?: string | null;       // yt-dlp --playlist-items syntax, e.g. "1-5,8,10-"
+    entry_ids?: string[] | null;
+    only_new?: boolean;          // Leave out entries already in the download archive
+}
+
+export interface PlaylistRequest {
+    url: string;
+    title: string;
+    playlist_id?: string | null;
+    entries: PlaylistEntry[];
+    selection?: PlaylistSelection;
+}
+
+export interface PlaylistChild {
+    task_id: string;
+    entry_id: string;
+    index: number;
+}
+
+export interface PlaylistEnqueued {
+    group_id: string;
+    children: PlaylistChild[]; // In playlist order
+    skipped: number;    // Left out as already downloaded
+}
+
+// "playlist-progress" event: aggregate of a group's child tasks
+export interface PlaylistProgressPayload {
+    id: string;
+    title: string;
+    url: string;
+    status: DownloadStatus;
+    progress: number;
+    total: number;
+    completed: number;
+    failed: number;
+    cancelled: number;
+    task_ids: string[];
+}
+
+export interface BulkResult {
+    id: string;
+    ok: boolean; // false = selected but could not be changed
+    status: DownloadStatus;
+}
+
+export interface OrphanedFile {
+    path: string;
+    size: number;
+    task_id: string; // The non-resumable task the fragment belongs to
+}
+
+export interface SweepReport {
+    files: OrphanedFile[];
+    reclaimable_bytes: number;
+}
+
 export interface VideoFormat {
     format_id: string;
     ext: string;
@@ -63,6 +148,8 @@ export interface PlaylistEntry {
     title: string;
     url: string;
     duration: number | null;
+    index?: number | null; // 1-based position in the playlist
+    archive_id?: string | null;
 }
 
 export interface VideoMetadata {
@@ -74,14 +161,241 @@ export interface VideoMetadata {
     formats: VideoFormat[];
     is_playlist?: boolean;
     entries?: PlaylistEntry[];
+    uploader?: string | null;
+    channel?: string | null;
+    upload_date?: string | null; // YYYYMMDD
+    extractor?: string | null;
+    archive_id?: string | null; // "<extractor> <id>", the download archive key
+    total_entries?: number | null; // Whole playlist, when the site reports it
+    has_more?: boolean;            // entries is only the first page; see streamPlaylist
+}
+
+export interface PlaylistPage {
+    url: string;
+    offset: number; // The first entry has index offset + 1
+    entries: PlaylistEntry[];
+    total: number | null;
+    has_more: boolean;
+}
+
+// "playlist-page" event, one per page of a streamPlaylist fetch
+export interface PlaylistPagePayload {
+    stream_id: string;
+    page: PlaylistPage | null;
+    error: string | null;
+    done: boolean;
+}
+
+export type FailureClass = 'network' | 'server_error' | 'rate_limited' | 'fatal' | 'unknown';
+
+export interface RetryPolicy {
+    max_attempts: number;
+    base_delay_ms: number;
+    max_delay_ms: number;
+    jitter: number;
+    retryable: FailureClass[];
+}
+
+export interface HistoryQuery {
+    statuses?: DownloadStatus[];
+    site?: string;
+    from?: number;   // Unix ms
+    to?: number;     // Unix ms
+    search?: string;
+    offset?: number;
+    limit?: number;
+}
+
+export interface HistoryRecord {
+    id: string;
+    url: string;
+    title: string;
+    status: DownloadStatus;
+    site: string | null;
+    extractor: string | null;
+    format_spec: string | null;
+    final_path: string | null;
+    created_at: number;
+    started_at: number | null;
+    finished_at: number | null;
+    file_size: number | null;
+    avg_speed: number | null; // Bytes per second
+    error_code: DownloadErrorCode | null;
+    error_message: string | null;
+    attempts: number;
+}
+
+export interface HistoryPage {
+    total: number;
+    records: HistoryRecord[];
+}
+
+export interface HostLimits {
+    per_host: number | null;           // Cap for hosts without an override; null = global limit only
+    overrides: Record<string, number>; // e.g. { "youtube.com": 1 }
+}
+
+export interface BandwidthLimits {
+    global: number | null;   // Bytes per second, split across running downloads
+    per_task: number | null; // Bytes per second
+}
+
+export type Weekday = 'Mon' | 'Tue' | 'Wed' | 'Thu' | 'Fri' | 'Sat' | 'Sun';
+
+export interface ScheduleWindow {
+    days: Weekday[];                     // Empty = every day
+    start: string;                       // "HH:MM", local time
+    end: string;                         // Before start = runs past midnight
+    bandwidth?: BandwidthLimits | null;  // Overrides the global limits inside the window
+}
+
+export interface Schedule {
+    enabled: boolean;
+    windows: ScheduleWindow[];
+}
+
+// 'suspend' stops the process in place (Unix only); 'restart' kills it and relaunches with --continue
+export type PauseStrategy = 'suspend' | 'restart';
+
+// What happens when the file a download would produce already exists.
+// 'rename' downloads to "Title (1).ext"; 'version' moves the old file to "Title (v1).ext"
+export type ConflictPolicy = 'skip' | 'overwrite' | 'rename' | 'version';
+
+export type ConflictOutcome =
+    | { outcome: 'skipped'; path: string }
+    | { outcome: 'overwritten'; path: string }
+    | { outcome: 'renamed'; path: string; original: string }
+    | { outcome: 'versioned'; path: string; previous: string };
+
+// For videos already in the download archive: refuse, enqueue with an
+// "already-downloaded" event, or ignore the archive
+export type ArchivePolicy = 'skip' | 'warn' | 'force';
+
+export interface ArchiveImport {
+    added: number;
+    invalid: number; // Lines that are not "<extractor> <id>"
+    total: number;
+}
+
+// "already-downloaded" event, for the 'warn' policy
+export interface ArchiveMatchPayload {
+    id: string;
+    title: string;
+    archive_id: string;
+}
+
+export type MediaKind = 'audio' | 'video';
+
+// Empty lists / null match anything; every non-empty criterion must match.
+// Rules only route downloads started without an explicit path.
+export interface RoutingRule {
+    name: string;
+    hosts: string[];
+    extractors: string[];
+    channels: string[];   // Channel/uploader names or @handles
+    playlists: string[];  // Playlist ids, e.g. YouTube's list= parameter
+    media: MediaKind | null;
+    output_dir: string;   // May start with ~
+}
+
+// What analysis already told us about a URL; lets rules match before yt-dlp runs
+export interface RouteContext {
+    extractor?: string | null;
+    channel?: string | null;
+    playlist?: string | null;
+}
+
+export interface RouteDecision {
+    output_dir: string | null; // null = no rule matched
+    rule: string | null;
+}
+
+// Bytes. A task starts only if its estimated size plus safety_margin fits;
+// running downloads pause when free space drops below min_free.
+export interface DiskSpace {
+    safety_margin: number;
+    min_free: number;
+}
+
+// "disk-space-low" event: a task held back (paused = false) or all downloads paused
+export interface DiskSpacePayload {
+    path: string;
+    available: number;
+    required: number;
+    task_ids: string[];
+    paused: boolean;
+}
+
+export interface AppSettings {
+    max_concurrent: number;
+    host_limits: HostLimits;
+    bandwidth: BandwidthLimits;
+    schedule: Schedule;
+    pause_strategy: PauseStrategy;
+    disk_space: DiskSpace;
+    // e.g. "{uploader}/{upload_date} - {title} [{id}].{ext}"; null = yt-dlp's default naming
+    output_template: string | null;
+    routing_rules: RoutingRule[];
+    conflict_policy: ConflictPolicy;
+    archive_policy: ArchivePolicy;
+    retry_policy: RetryPolicy;
 }
 
 export interface IDownloadService {
     getVideoMetadata(url: string): Promise<VideoMetadata>;
-    startDownload(url: string, options: { title: string, path?: string | null, format?: string | null, cookies?: string | null }): Promise<string>;
+    // limit must be between 1 and the backend's playlist page size (100)
+    getPlaylistPage(url: string, offset: number, limit: number): Promise<PlaylistPage>;
+    // Resolves to a stream id; pages arrive as "playlist-page" events
+    streamPlaylist(url: string, offset: number, pageSize?: number): Promise<string>;
+    cancelPlaylistStream(id: string): Promise<void>;
+    // One child task per selected entry under a new playlist group
+    enqueuePlaylist(request: PlaylistRequest, options: { path?: string | null, format?: string | null, cookies?: string | null, priority?: Priority }): Promise<PlaylistEnqueued>;
+    listPlaylists(): Promise<PlaylistProgressPayload[]>;
+    cancelPlaylist(id: string): Promise<BulkResult[]>;
+    retryPlaylist(id: string): Promise<BulkResult[]>; // Failed entries only
+    startDownload(url: string, options: { title: string, path?: string | null, format?: string | null, cookies?: string | null, extraArgs?: string[], priority?: Priority, rateLimit?: number | null, ignoreSchedule?: boolean, estimatedSize?: number | null, outputTemplate?: string | null, conflictPolicy?: ConflictPolicy | null, archiveId?: string | null, ignoreArchive?: boolean, route?: RouteContext }): Promise<string>;
     pauseDownload(id: string): Promise<void>;
-    resumeDownload(id: string): Promise<void>;
+    resumeDownload(id: string): Promise<DownloadStatus>;
+    retryDownload(id: string): Promise<void>;
+    pauseAll(): Promise<BulkResult[]>;
+    resumeAll(): Promise<BulkResult[]>;
+    cancelQueued(): Promise<BulkResult[]>;
+    retryFailed(): Promise<BulkResult[]>;
+    clearCompleted(): Promise<BulkResult[]>;
+    removeDownload(id: string, deleteFiles: boolean): Promise<void>;
+    // Without statuses in the filter, only completed/failed/cancelled tasks are cleared
+    clearHistory(filter: HistoryQuery, deleteFiles: boolean): Promise<BulkResult[]>;
+    // Leftover .part/.ytdl/.temp files of downloads that cannot be resumed
+    scanOrphanedFiles(): Promise<SweepReport>;
+    deleteOrphanedFiles(paths: string[]): Promise<SweepReport>;
+    // Queue commands resolve to the waiting task ids in scheduling order
+    getQueue(): Promise<string[]>;
+    setPriority(id: string, priority: Priority): Promise<string[]>;
+    reorderDownload(id: string, index: number): Promise<string[]>;
+    moveDownloadToTop(id: string): Promise<string[]>;
+    moveDownloadToBottom(id: string): Promise<string[]>;
+    getRetryPolicy(): Promise<RetryPolicy>;
+    setRetryPolicy(policy: RetryPolicy): Promise<void>;
+    getSettings(): Promise<AppSettings>;
+    setMaxConcurrent(max: number): Promise<void>;
+    setHostLimits(limits: HostLimits): Promise<void>;
+    setBandwidthLimits(limits: BandwidthLimits): Promise<void>;
+    setSchedule(schedule: Schedule): Promise<void>;
+    setPauseStrategy(strategy: PauseStrategy): Promise<void>;
+    setDiskSpace(diskSpace: DiskSpace): Promise<void>;
+    setOutputTemplate(template: string | null): Promise<void>;
+    setRoutingRules(rules: RoutingRule[]): Promise<void>;
+    setConflictPolicy(policy: ConflictPolicy): Promise<void>;
+    setArchivePolicy(policy: ArchivePolicy): Promise<void>;
+    // Files in yt-dlp's --download-archive format
+    importDownloadArchive(path: string): Promise<ArchiveImport>;
+    exportDownloadArchive(path: string): Promise<number>;
+    resolveOutputDir(url: string, formatSpec: string | null, route?: RouteContext): Promise<RouteDecision>;
+    // Relative path the download would get; without a template the current default is used
+    previewOutputTemplate(template: string | null, metadata: VideoMetadata, formatSpec: string | null): Promise<string>;
+    startDownloadNow(id: string): Promise<void>;
     cancelDownload(id: string): Promise<void>;
     listDownloads(): Promise<Download[]>;
+    queryHistory(query: HistoryQuery): Promise<HistoryPage>;
     showInFolder(path: string): Promise<void>;
 }
@@ -92,7 +92,7 @@ export const FormatSelector = ({ metadata, onConfirm, onCancel }: FormatSelector
                         <button
                             className="flex-1 flex items-center justify-center gap-2 py-2 text-xs font-bold rounded-lg bg-primary text-white"
                         >
-                            <ListIcon size={14} /> Playlist ({metadata.entries?.length})
+                            <ListIcon size={14} /> Playlist ({metadata.entries?.length}{metadata.has_more ? ` of ${metadata.total_entries ?? 'more'}` : ''})
                         </button>
                     )}
                 </div>
@@ -172,9 +172,9 @@ export const FormatSelector = ({ metadata, onConfirm, onCancel }: FormatSelector
                                         <span className="text-xs text-slate-700 dark:text-gray-300 truncate flex-1 font-medium">{entry.title}</span>
                                     </div>
                                 ))}
-                                {metadata.entries && metadata.entries.length > 50 && (
+                                {metadata.entries && Math.max(metadata.entries.length, metadata.total_entries ?? 0) > 50 && (
                                     <div className="p-2 text-center text-[10px] text-slate-400 dark:text-gray-500 bg-gray-100 dark:bg-black/20 italic">
-                                        + {metadata.entries.length - 50} more items
+                                        + {Math.max(metadata.entries.length, metadata.total_entries ?? 0) - 50} more items
                                     </div>
                                 )}
                             </div>